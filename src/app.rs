@@ -1,15 +1,23 @@
 use crate::Lesson;
+use crate::config::config;
 use crate::formatter::HelixFormatter;
+use crate::helix_cli::HelixCli;
 use crate::lessons::get_lesson;
-use crate::ui::{clear_screen, display_lesson, get_user_input};
+use crate::project::project;
+use crate::theme::{Themed, Tone};
+use crate::ui::{clear_screen, get_user_input, watch_resize};
 use crate::validation::{
-    ParsedQueries, ParsedSchema, QueryValidator, check_helix_init, get_completed_lessons,
-    get_current_lesson, mark_lesson_completed, redeploy_instance,
-    save_current_lesson,
+    DeployReport, ParsedQueries, ParsedSchema, QueryCase, QueryValidator, SeedReference,
+    check_helix_init, deploy_if_changed, get_completed_lessons, get_current_lesson,
+    mark_lesson_completed, reset_created_entities, save_current_lesson, watch_interrupts,
 };
 use colored::*;
 use std::collections::HashMap;
-use std::process::Command;
+
+/// The first lesson whose queries read or write data.
+const FIRST_QUERY_LESSON: usize = 5;
+/// The lesson whose reference answer is the complete schema.
+const SCHEMA_LESSON: u32 = 4;
 
 pub enum ActionResult {
     Continue,
@@ -18,6 +26,7 @@ pub enum ActionResult {
 }
 
 pub enum MenuAction {
+    Init,
     Next,
     Back,
     Help,
@@ -26,6 +35,9 @@ pub enum MenuAction {
     GoToLesson(usize),
     RunPreviousLessons,
     ShowProgress,
+    ShowLastLog,
+    RemoveDuplicates,
+    Seed,
 }
 
 pub struct App {
@@ -35,10 +47,12 @@ pub struct App {
     max_lessons: usize,
     formatter: HelixFormatter,
     output_messages: Vec<String>,
+    last_deploy_log: Option<String>,
+    helix: Box<dyn HelixCli>,
 }
 
 impl App {
-    pub fn new(lessons: HashMap<u32, Lesson>) -> Self {
+    pub fn new(lessons: HashMap<u32, Lesson>, helix: Box<dyn HelixCli>) -> Self {
         let max_lessons = 24;
 
         Self {
@@ -47,6 +61,8 @@ impl App {
             max_lessons,
             formatter: HelixFormatter::new(),
             output_messages: Vec::new(),
+            last_deploy_log: None,
+            helix,
         }
     }
 
@@ -70,6 +86,9 @@ impl App {
         self.handle_welcome_selection(initial_selection);
 
         let runtime = tokio::runtime::Runtime::new().expect("Failed to create tokio runtime");
+        runtime.spawn(watch_interrupts());
+        runtime.spawn(watch_resize());
+        self.enter_lesson(&runtime);
         loop {
             let command = get_user_input();
             let action = self.parse_command(&command);
@@ -84,8 +103,7 @@ impl App {
                         ActionResult::ChangeTo(new_lesson) => {
                             self.current_lesson = new_lesson;
                             let _ = save_current_lesson(self.current_lesson);
-                            self.clear_output();
-                            self.display_current_lesson();
+                            self.enter_lesson(&runtime);
                         }
                         ActionResult::Exit => {
                             self.formatter.display_info("Thanks for using Helixir :)");
@@ -107,21 +125,7 @@ impl App {
         let trimmed = input.trim();
 
         if self.current_lesson == 0 && trimmed == "helix init" {
-            let output = Command::new("helix").arg("init").output();
-            match output {
-                Ok(result) => {
-                    if result.status.success() {
-                        return Ok(MenuAction::Check);
-                    } else {
-                        println!("Helix init command completed, but check if it was successful.");
-                        return Ok(MenuAction::Check);
-                    }
-                }
-                Err(_) => {
-                    println!("Please install helix-db and it's CLI");
-                    return Err("helix init failed - HelixDB CLI not installed".to_string());
-                }
-            }
+            return Ok(MenuAction::Init);
         }
 
         match trimmed.to_lowercase().as_str() {
@@ -132,6 +136,9 @@ impl App {
             "q" => Ok(MenuAction::Quit),
             "p" => Ok(MenuAction::ShowProgress),
             "r" => Ok(MenuAction::RunPreviousLessons),
+            "l" => Ok(MenuAction::ShowLastLog),
+            "d" => Ok(MenuAction::RemoveDuplicates),
+            "s" => Ok(MenuAction::Seed),
             cmd if cmd.starts_with("g ") => {
                 let lesson_str = cmd.strip_prefix("g ").unwrap();
                 match lesson_str.parse::<usize>() {
@@ -151,6 +158,25 @@ impl App {
 
     async fn handle_action(&mut self, action: MenuAction) -> ActionResult {
         match action {
+            MenuAction::Init => match self.helix.init().await {
+                Ok(result) => {
+                    if !result.success {
+                        self.add_output(
+                            "Helix init command completed, but check if it was successful."
+                                .to_string(),
+                        );
+                    }
+                    Box::pin(self.handle_action(MenuAction::Check)).await
+                }
+                Err(_) => {
+                    clear_screen();
+                    self.add_output("Please install helix-db and it's CLI".to_string());
+                    self.add_output(
+                        "[ERROR] helix init failed - HelixDB CLI not installed".to_string(),
+                    );
+                    ActionResult::Continue
+                }
+            },
             MenuAction::Back => {
                 if self.current_lesson == 0 {
                     clear_screen();
@@ -165,7 +191,7 @@ impl App {
             }
             MenuAction::Check => {
                 clear_screen();
-                let _lesson = get_lesson(self.current_lesson);
+                let lesson = get_lesson(self.current_lesson);
 
                 if self.current_lesson >= 5 {
                     let expected_hql = self
@@ -174,15 +200,21 @@ impl App {
                         .expect("Lesson HQL data should be compiled into binary");
 
                     match (
-                        ParsedQueries::from_file("db/queries.hx"),
+                        ParsedQueries::from_dir(&project().queries_dir),
                         ParsedQueries::from_string(expected_hql),
                     ) {
                         (Ok(user_queries), Ok(expected_queries)) => {
+                            // earlier lessons' queries are only rechecked in strict mode
+                            let scope = if config().strict {
+                                None
+                            } else {
+                                lesson.query_name.as_deref()
+                            };
                             let validation_result =
-                                user_queries.validate_against(&expected_queries);
+                                user_queries.validate_against(&expected_queries, scope);
 
                             if !validation_result.is_correct {
-                                self.add_output("[INCORRECT] Query validation failed. Please fix your queries.hx file".to_string());
+                                self.add_output("[INCORRECT] Query validation failed. Please fix your .hx files".to_string());
 
                                 if !validation_result.missing_queries.is_empty() {
                                     self.add_output(format!(
@@ -196,10 +228,15 @@ impl App {
                                         validation_result.extra_queries
                                     ));
                                 }
-                                for (query_name, error) in &validation_result.query_errors {
+                                for (query_name, mismatch) in &validation_result.query_errors {
                                     self.add_output(format!(
-                                        "[ERROR] Query '{}': {}",
-                                        query_name, error
+                                        "[ERROR] Query '{}': {}\n{}",
+                                        query_name,
+                                        mismatch.errors.join(". "),
+                                        self.formatter.render_query_diff(
+                                            &mismatch.expected_source,
+                                            &mismatch.actual_source
+                                        )
                                     ));
                                 }
                                 return ActionResult::Continue;
@@ -209,10 +246,7 @@ impl App {
                             );
                         }
                         (Err(e), _) => {
-                            self.add_output(format!(
-                                "[ERROR] Could not parse your queries.hx file: {}",
-                                e
-                            ));
+                            self.add_output(format!("[ERROR] Could not parse your queries: {}", e));
                             return ActionResult::Continue;
                         }
                         (_, Err(e)) => {
@@ -225,12 +259,31 @@ impl App {
                     }
 
                     self.add_output("Deploying queries to cluster...".to_string());
-                    if !redeploy_instance() {
+                    let report = self.deploy().await;
+                    if report.cancelled {
+                        self.add_output("Deploy cancelled, back to the lesson.".to_string());
+                        return ActionResult::Continue;
+                    }
+                    if report.skipped {
+                        self.add_output(
+                            "No changes since the last deploy, skipping the rebuild.".to_string(),
+                        );
+                    }
+                    for line in deploy_messages(&report) {
+                        self.add_output(line);
+                    }
+                    if !report.success {
                         self.add_output(
                             "[ERROR] Cannot proceed without successful deployment".to_string(),
                         );
                         return ActionResult::Continue;
                     }
+                    if self.current_lesson > FIRST_QUERY_LESSON
+                        && self.report_stale_entities().await
+                    {
+                        return ActionResult::Continue;
+                    }
+                    self.report_duplicate_nodes().await;
                     self.add_output("Running database queries...".to_string());
 
                     let lesson_data = self
@@ -240,6 +293,7 @@ impl App {
                     let lesson_json: serde_json::Value = serde_json::from_str(lesson_data).unwrap();
 
                     let queries = lesson_json["queries"].as_array().unwrap();
+                    let query_instance = QueryValidator::new();
                     for (index, query_test) in queries.iter().enumerate() {
                         let query_name = query_test["query_name"].as_str().unwrap();
                         let input = query_test["input"].clone();
@@ -250,7 +304,6 @@ impl App {
                             queries.len(),
                             query_name
                         ));
-                        let query_instance: QueryValidator = QueryValidator::new();
                         let comparison =
                             query_instance.execute_and_compare(query_name, input).await;
                         match comparison {
@@ -263,23 +316,15 @@ impl App {
                                 if !success {
                                     return ActionResult::Continue;
                                 }
+                                if !self
+                                    .run_query_cases(&query_instance, query_name, query_test)
+                                    .await
+                                {
+                                    return ActionResult::Continue;
+                                }
                             }
                             Err(e) => {
-                                let error_msg = e.to_string();
-                                if error_msg.contains("error decoding response body") {
-                                    self.add_output(format!(
-                                        "[ERROR] Deserialization error in query {}: {}",
-                                        query_name, error_msg
-                                    ));
-                                    self.add_output(
-                                        "[ERROR] Check if server response format matches lesson_types.rs structures".to_string()
-                                    );
-                                } else {
-                                    self.add_output(format!(
-                                        "[ERROR] Query execution failed: {}",
-                                        error_msg
-                                    ));
-                                }
+                                self.add_output(format!("[ERROR] Query execution failed: {}", e));
                                 return ActionResult::Continue;
                             }
                         }
@@ -296,7 +341,7 @@ impl App {
                         .expect("Lesson HQL data should be compiled into binary");
 
                     match (
-                        ParsedSchema::from_file("db/schema.hx"),
+                        ParsedSchema::from_dir(&project().queries_dir),
                         ParsedSchema::from_string(expected_hql),
                     ) {
                         (Ok(user_schema), Ok(expected_schema)) => {
@@ -438,10 +483,10 @@ impl App {
                         (_, Err(e)) => self
                             .add_output(format!("[ERROR] Could not load expected schema: {}", e)),
                     }
-                    return ActionResult::Continue;
+                    ActionResult::Continue
                 } else if self.current_lesson == 0 {
-                    match Command::new("helix").arg("check").output() {
-                        Ok(output) if output.status.success() => {
+                    match self.helix.check().await {
+                        Ok(output) if output.success => {
                             let _ = mark_lesson_completed(self.current_lesson);
                             self.add_output(
                                 "[CORRECT] Helix initialization completed! Lesson completed!"
@@ -500,14 +545,319 @@ impl App {
                 clear_screen();
                 self.run_previous_lessons().await
             }
+            MenuAction::ShowLastLog => {
+                clear_screen();
+                match self.last_deploy_log.clone() {
+                    Some(log) => {
+                        self.add_output("Last deploy log:".to_string());
+                        for line in log.lines() {
+                            self.add_output(line.to_string());
+                        }
+                    }
+                    None => {
+                        self.add_output("No deploy has been run in this session yet.".to_string())
+                    }
+                }
+                ActionResult::Continue
+            }
+            MenuAction::RemoveDuplicates => {
+                clear_screen();
+                self.remove_duplicate_nodes().await;
+                ActionResult::Continue
+            }
+            MenuAction::Seed => {
+                clear_screen();
+                self.seed(self.current_lesson).await;
+                ActionResult::Continue
+            }
         }
     }
+
+    /// Runs the extra `cases` of a query test, stopping at the first that
+    /// fails. Returns whether they all passed.
+    async fn run_query_cases(
+        &mut self,
+        validator: &QueryValidator,
+        query_name: &str,
+        query_test: &serde_json::Value,
+    ) -> bool {
+        let Some(cases) = query_test.get("cases") else {
+            return true;
+        };
+        let cases: Vec<QueryCase> = match serde_json::from_value(cases.clone()) {
+            Ok(cases) => cases,
+            Err(e) => {
+                self.add_output(format!(
+                    "[ERROR] Invalid test cases for query {}: {}",
+                    query_name, e
+                ));
+                return false;
+            }
+        };
+
+        for case in &cases {
+            match validator.run_case(query_name, case).await {
+                Ok((true, message)) => self.add_output(format!("[CORRECT] Case: {}", message)),
+                Ok((false, message)) => {
+                    self.add_output(format!("[INCORRECT] Case: {}", message));
+                    return false;
+                }
+                Err(e) => {
+                    self.add_output(format!("[ERROR] Case '{}' failed: {}", case.description, e));
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    /// The embedded answers `seed` rebuilds the graph from.
+    fn seed_reference(&self) -> Option<SeedReference<'_>> {
+        Some(SeedReference {
+            schema: &self.get_lesson_answers(SCHEMA_LESSON)?.hql_answer,
+            queries: (FIRST_QUERY_LESSON..=self.max_lessons)
+                .filter_map(|lesson_id| self.get_lesson_answers(lesson_id as u32))
+                .map(|answers| answers.hql_answer.as_str())
+                .collect(),
+        })
+    }
+
+    /// Wipes the instance and replays the reference answers of every lesson
+    /// before `lesson_id`, so the lesson starts from the graph it expects.
+    async fn seed(&mut self, lesson_id: usize) {
+        if lesson_id < FIRST_QUERY_LESSON {
+            self.add_output(format!(
+                "Lessons before {} don't use any data, so there is nothing to seed.",
+                FIRST_QUERY_LESSON
+            ));
+            return;
+        }
+
+        let report = self.deploy().await;
+        if report.cancelled {
+            self.add_output("Deploy cancelled, the database was not seeded.".to_string());
+            return;
+        }
+        if !report.success {
+            self.add_output(format!(
+                "[ERROR] Could not seed lesson {}: your queries failed to deploy",
+                lesson_id
+            ));
+            for line in deploy_messages(&report) {
+                self.add_output(line);
+            }
+            return;
+        }
+
+        let schema = match ParsedSchema::from_dir(&project().queries_dir) {
+            Ok(schema) => schema,
+            Err(e) => {
+                self.add_output(format!("[ERROR] Could not load your schema: {}", e));
+                return;
+            }
+        };
+        if self
+            .seed_reference()
+            .and_then(|seed| seed.queries_for(&schema))
+            .is_none()
+        {
+            self.add_output(format!(
+                "[ERROR] Seeding needs the schema from lessons 1-{}. Finish those lessons first",
+                SCHEMA_LESSON
+            ));
+            return;
+        }
+
+        match self.replay_lessons(lesson_id, &schema).await {
+            Ok(0) => self.add_output(format!(
+                "[CORRECT] Cleared the database for lesson {}",
+                lesson_id
+            )),
+            Ok(replayed) => self.add_output(format!(
+                "[CORRECT] Seeded the database for lesson {} by replaying {} queries from lessons {}-{}",
+                lesson_id,
+                replayed,
+                FIRST_QUERY_LESSON,
+                lesson_id - 1
+            )),
+            Err(e) => {
+                self.add_output(format!("[ERROR] Could not seed lesson {}: {}", lesson_id, e));
+                self.add_output("Press 's' to try seeding again".to_string());
+            }
+        }
+    }
+
+    /// Clears the instance and instance.json, then runs the reference answers
+    /// of lessons before `lesson_id` in order. Returns how many queries ran.
+    async fn replay_lessons(
+        &self,
+        lesson_id: usize,
+        schema: &ParsedSchema,
+    ) -> Result<usize, String> {
+        QueryValidator::new()
+            .wipe(schema)
+            .await
+            .map_err(|e| format!("could not clear the instance: {}", e))?;
+        reset_created_entities()?;
+
+        let seeder = QueryValidator::seeding();
+        let mut replayed = 0;
+        for previous in FIRST_QUERY_LESSON..lesson_id {
+            let Some(answers) = self.get_lesson_answers(previous as u32) else {
+                continue;
+            };
+            if answers.query_answer.is_empty() {
+                continue;
+            }
+            let lesson_json: serde_json::Value = serde_json::from_str(&answers.query_answer)
+                .map_err(|e| format!("could not parse lesson {} JSON: {}", previous, e))?;
+
+            for query_test in lesson_json["queries"].as_array().into_iter().flatten() {
+                let query_name = query_test["query_name"].as_str().unwrap_or("unknown");
+                match seeder
+                    .execute_and_compare(query_name, query_test["input"].clone())
+                    .await
+                {
+                    Ok((true, _)) => replayed += 1,
+                    Ok((false, message)) => {
+                        return Err(format!(
+                            "{} from lesson {} failed: {}",
+                            query_name, previous, message
+                        ));
+                    }
+                    Err(e) => {
+                        return Err(format!(
+                            "{} from lesson {} failed: {}",
+                            query_name, previous, e
+                        ));
+                    }
+                }
+            }
+        }
+
+        Ok(replayed)
+    }
+
+    async fn remove_duplicate_nodes(&mut self) {
+        let schema = match ParsedSchema::from_dir(&project().queries_dir) {
+            Ok(schema) => schema,
+            Err(e) => {
+                self.add_output(format!("[ERROR] Could not load your schema: {}", e));
+                return;
+            }
+        };
+
+        let validator = QueryValidator::new();
+        let result = match validator.find_duplicate_nodes(&schema).await {
+            Ok(duplicates) if duplicates.is_empty() => {
+                self.add_output("No duplicate nodes found.".to_string());
+                return;
+            }
+            Ok(duplicates) => validator.remove_duplicate_nodes(&duplicates).await,
+            Err(e) => Err(e),
+        };
+
+        match result {
+            Ok(removed) => self.add_output(format!(
+                "[CORRECT] Removed {} duplicate node{}",
+                removed,
+                if removed == 1 { "" } else { "s" }
+            )),
+            Err(e) => {
+                self.add_output(format!("[ERROR] Could not remove duplicates: {}", e));
+                self.add_output(
+                    "Press 'c' to deploy your queries first, then try again".to_string(),
+                );
+            }
+        }
+    }
+
+    /// Warns when the ids in instance.json no longer exist, which would make
+    /// every query that looks them up fail. Returns whether any were stale.
+    async fn report_stale_entities(&mut self) -> bool {
+        let Ok(stale) = QueryValidator::new().stale_created_entities().await else {
+            return false;
+        };
+        if stale.is_empty() {
+            return false;
+        }
+
+        self.add_output(format!(
+            "[WARNING] {} of the entities recorded in instance.json no longer exist in the instance.",
+            stale.len()
+        ));
+        self.add_output(
+            "[WARNING] It was probably rebuilt or restarted with an empty store:".to_string(),
+        );
+        for entity in &stale {
+            match &entity.name {
+                Some(name) => self.add_output(format!(
+                    "[WARNING]   {} '{}' ({})",
+                    entity.label, name, entity.id
+                )),
+                None => self.add_output(format!("[WARNING]   {} {}", entity.label, entity.id)),
+            }
+        }
+        self.add_output(
+            "Press 's' to re-seed the database for this lesson, or 'r' to re-run the lessons that create them"
+                .to_string(),
+        );
+        true
+    }
+
+    /// Warns about nodes left duplicated by earlier runs, which would skew
+    /// counts in later lessons.
+    async fn report_duplicate_nodes(&mut self) {
+        let Ok(schema) = ParsedSchema::from_dir(&project().queries_dir) else {
+            return;
+        };
+        let Ok(duplicates) = QueryValidator::new().find_duplicate_nodes(&schema).await else {
+            return;
+        };
+        if duplicates.is_empty() {
+            return;
+        }
+
+        self.add_output("[WARNING] Found duplicate nodes from earlier runs:".to_string());
+        for group in &duplicates {
+            self.add_output(format!(
+                "[WARNING]   {} '{}': {} copies",
+                group.label,
+                group.name,
+                group.extra.len() + 1
+            ));
+        }
+        self.add_output("Press 'd' to remove the extra copies".to_string());
+    }
+
+    /// Runs a deploy and keeps its raw log around for the `l` command.
+    async fn deploy(&mut self) -> DeployReport {
+        let seed = self.seed_reference();
+        let report =
+            deploy_if_changed(self.helix.as_ref(), config().deploy_timeout, seed.as_ref()).await;
+        // keep the log of the deploy that actually produced the running instance
+        if report.skipped && self.last_deploy_log.is_some() {
+            return report;
+        }
+        self.last_deploy_log = Some(report.log.clone());
+        report
+    }
     fn clear_output(&mut self) {
         self.output_messages.clear();
     }
     fn add_output(&mut self, message: String) {
         self.output_messages.push(message);
     }
+    /// Shows the current lesson, seeding the graph first when the lesson
+    /// works with data, so every way into a lesson starts from its data.
+    fn enter_lesson(&mut self, runtime: &tokio::runtime::Runtime) {
+        self.clear_output();
+        if self.current_lesson >= FIRST_QUERY_LESSON {
+            runtime.block_on(self.seed(self.current_lesson));
+        }
+        self.display_current_lesson();
+    }
+
     fn display_current_lesson(&self) {
         let lesson = get_lesson(self.current_lesson);
         if self.output_messages.is_empty() {
@@ -536,18 +886,18 @@ impl App {
             .display_info(&format!("Current lesson: {}", self.current_lesson));
 
         if !completed_lessons.is_empty() {
-            println!("{}", "Completed lessons:".bright_green().bold());
+            println!("{}", "Completed lessons:".themed(Tone::Green).bold());
             for lesson_id in completed_lessons {
                 let lesson = get_lesson(lesson_id);
-                println!("  {} - {}", lesson_id, lesson.title.bright_white());
+                println!("  {} - {}", lesson_id, lesson.title.themed(Tone::Text));
             }
         } else {
-            println!("{}", "No lessons completed yet.".bright_yellow());
+            println!("{}", "No lessons completed yet.".themed(Tone::Yellow));
         }
         println!();
     }
 
-    async fn run_previous_lessons(&self) -> ActionResult {
+    async fn run_previous_lessons(&mut self) -> ActionResult {
         if self.current_lesson == 0 {
             self.formatter.display_info("No previous lessons to run.");
             return ActionResult::Continue;
@@ -569,9 +919,18 @@ impl App {
                 .display_info(&format!("Running lesson {}: {}", lesson_id, lesson.title));
 
             if lesson_id >= 5 {
-                if !redeploy_instance() {
-                    self.formatter
-                        .display_error(&format!("Failed to compile for lesson {}", lesson_id));
+                let report = self.deploy().await;
+                if report.cancelled {
+                    self.formatter.display_info("Deploy cancelled.");
+                    return ActionResult::Continue;
+                }
+                if !report.success {
+                    let mut message = format!("Failed to compile for lesson {}", lesson_id);
+                    for line in deploy_messages(&report) {
+                        message.push('\n');
+                        message.push_str(&line);
+                    }
+                    self.formatter.display_error(&message);
                     continue;
                 }
 
@@ -602,15 +961,19 @@ impl App {
                 };
 
                 if let Some(queries) = lesson_json["queries"].as_array() {
+                    let query_instance = QueryValidator::new();
                     for query_test in queries {
                         let query_name = query_test["query_name"].as_str().unwrap_or("unknown");
                         let input = query_test["input"].clone();
 
-                        let query_instance = QueryValidator::new();
                         match query_instance.execute_and_compare(query_name, input).await {
                             Ok((success, message)) => {
                                 if success {
-                                    println!("  {} {}", "[OK]".bright_green().bold(), query_name);
+                                    println!(
+                                        "  {} {}",
+                                        self.formatter.status_tag("[OK]"),
+                                        query_name
+                                    );
                                 } else {
                                     self.formatter.display_error(&format!(
                                         "Query {} failed: {}",
@@ -638,50 +1001,43 @@ impl App {
         if has_progress {
             println!(
                 "{}",
-                "  What would you like to do?"
-                    .truecolor(202, 211, 245)
-                    .bold()
+                "  What would you like to do?".themed(Tone::Text).bold()
             );
             println!();
             println!(
                 "{} {}",
-                "  1)".truecolor(166, 218, 149).bold(),
-                format!("Resume from lesson {}", self.current_lesson).truecolor(184, 192, 224)
+                "  1)".themed(Tone::Green).bold(),
+                format!("Resume from lesson {}", self.current_lesson).themed(Tone::Subtext)
             );
             println!(
                 "{} {}",
-                "  2)".truecolor(166, 218, 149).bold(),
-                "Go to specific lesson".truecolor(184, 192, 224)
+                "  2)".themed(Tone::Green).bold(),
+                "Go to specific lesson".themed(Tone::Subtext)
             );
             println!(
                 "{} {}",
-                "  3)".truecolor(166, 218, 149).bold(),
-                "Start from beginning".truecolor(184, 192, 224)
+                "  3)".themed(Tone::Green).bold(),
+                "Start from beginning".themed(Tone::Subtext)
             );
         } else {
             println!(
                 "{}",
-                "  What would you like to do?"
-                    .truecolor(202, 211, 245)
-                    .bold()
+                "  What would you like to do?".themed(Tone::Text).bold()
             );
             println!();
             println!(
                 "{} {}",
-                "  1)".truecolor(166, 218, 149).bold(),
-                "Get started (Lesson 0)".truecolor(184, 192, 224)
+                "  1)".themed(Tone::Green).bold(),
+                "Get started (Lesson 0)".themed(Tone::Subtext)
             );
             println!(
                 "{} {}",
-                "  2)".truecolor(166, 218, 149).bold(),
-                "Go to specific lesson".truecolor(184, 192, 224)
+                "  2)".themed(Tone::Green).bold(),
+                "Go to specific lesson".themed(Tone::Subtext)
             );
         }
         println!();
-        print!(
-            "{}",
-            "  Enter your choice: ".truecolor(184, 192, 224).bold()
-        );
+        print!("{}", "  Enter your choice: ".themed(Tone::Subtext).bold());
     }
     fn get_welcome_input(&self) -> String {
         use std::io::{self, Write};
@@ -695,15 +1051,12 @@ impl App {
 
         match selection.as_str() {
             "1" => {
-                if check_helix_init() {
-                    display_lesson(self.current_lesson);
-                } else {
+                if !check_helix_init() {
                     self.current_lesson = 0;
-                    display_lesson(self.current_lesson);
                 }
             }
             "2" => {
-                print!("{}", "Enter lesson number: ".bright_yellow());
+                print!("{}", "Enter lesson number: ".themed(Tone::Yellow));
                 use std::io::{self, Write};
                 io::stdout().flush().unwrap();
                 let mut input = String::new();
@@ -714,32 +1067,50 @@ impl App {
                         self.current_lesson = lesson_num;
                         let _ = save_current_lesson(self.current_lesson);
                         clear_screen();
-                        display_lesson(self.current_lesson);
                     } else {
                         println!(
                             "{}",
                             format!("Invalid lesson number. Max lesson is {}", self.max_lessons)
-                                .bright_red()
+                                .themed(Tone::Red)
                         );
                         self.current_lesson = 0;
-                        display_lesson(self.current_lesson);
                     }
                 } else {
-                    println!("{}", "Invalid input. Starting from lesson 0.".bright_red());
+                    println!(
+                        "{}",
+                        "Invalid input. Starting from lesson 0.".themed(Tone::Red)
+                    );
                     self.current_lesson = 0;
-                    display_lesson(self.current_lesson);
                 }
             }
             "3" if check_helix_init() => {
                 self.current_lesson = 0;
                 let _ = save_current_lesson(self.current_lesson);
-                display_lesson(self.current_lesson);
             }
             _ => {
-                println!("{}", "Invalid choice. Starting from lesson 0.".bright_red());
+                println!(
+                    "{}",
+                    "Invalid choice. Starting from lesson 0.".themed(Tone::Red)
+                );
                 self.current_lesson = 0;
-                display_lesson(self.current_lesson);
             }
         }
     }
 }
+
+/// Formats deploy diagnostics for the OUTPUT panel.
+fn deploy_messages(report: &DeployReport) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    for diagnostic in &report.diagnostics {
+        lines.push(format!("[ERROR] {}", diagnostic));
+        if let Some(suggestion) = &diagnostic.suggestion {
+            lines.push(format!("        Suggestion: {}", suggestion));
+        }
+    }
+    if !report.success {
+        lines.push("Press 'l' to see the full deploy log".to_string());
+    }
+
+    lines
+}
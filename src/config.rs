@@ -0,0 +1,415 @@
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::project::Project;
+use crate::theme::Theme;
+use crate::validation::SearchMetric;
+
+const DEFAULT_HOST: &str = "http://localhost";
+const DEFAULT_PORT: u16 = 6969;
+const DEFAULT_HELIX_BINARY: &str = "helix";
+const DEFAULT_DEPLOY_TIMEOUT_SECS: u64 = 600;
+const DEFAULT_RECALL_THRESHOLD: f64 = 1.0;
+const DEFAULT_RELATIVE_TOLERANCE: f64 = 1e-6;
+const DEFAULT_ABSOLUTE_TOLERANCE: f64 = 1e-9;
+
+pub const USAGE: &str = "Usage: helixir [OPTIONS]
+
+Options:
+  --project <DIR>           Helix project to work in, by default the nearest parent
+                            directory with a helix.toml, db/ or instance.json
+  --endpoint <URL>          URL of the Helix instance, by default localhost on the
+                            instance's port in helix.toml [env: HELIXIR_ENDPOINT]
+  --api-key <KEY>           API key sent with every query [env: HELIXIR_API_KEY]
+  --instance <NAME>         Instance from helix.toml to build and push
+                            [env: HELIXIR_INSTANCE]
+  --helix-bin <PATH>        Path to the helix binary [env: HELIXIR_HELIX_BIN]
+  --deploy-timeout <SECS>   Seconds to wait for a deploy [env: HELIXIR_DEPLOY_TIMEOUT]
+  --strict                  Check every query from earlier lessons too, not just the
+                            current lesson's [env: HELIXIR_STRICT]
+  --search-metric <METRIC>  Distance used to rank vector search results, cosine or
+                            euclidean [env: HELIXIR_SEARCH_METRIC]
+  --recall-threshold <0-1>  Share of the exact nearest neighbours a vector search must
+                            return [env: HELIXIR_RECALL_THRESHOLD]
+  --relative-tolerance <EPS>
+                            Relative difference allowed between F64 values and
+                            vectors that should be equal [env: HELIXIR_RELATIVE_TOLERANCE]
+  --absolute-tolerance <EPS>
+                            Absolute difference allowed between F64 values near zero
+                            [env: HELIXIR_ABSOLUTE_TOLERANCE]
+  --theme <THEME>           Colours to draw in: dark, light, high-contrast or
+                            monochrome [env: HELIXIR_THEME]
+  --accessible              Output for screen readers: no screen clears, box drawing
+                            or colour-only cues [env: HELIXIR_ACCESSIBLE]
+  -h, --help                Print this help
+
+Setting NO_COLOR turns colours off, whatever the theme.
+
+Settings not given as flags or environment variables are read from
+~/.config/helixir/config.toml, using the flag names with underscores
+(e.g. api_key = \"...\").";
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub endpoint: String,
+    pub api_key: Option<String>,
+    pub instance: String,
+    pub helix_binary: PathBuf,
+    pub deploy_timeout: Duration,
+    /// Validate all queries up to the current lesson instead of only its own.
+    pub strict: bool,
+    pub search_metric: SearchMetric,
+    /// Below 1.0 a vector search may miss some of the exact nearest
+    /// neighbours, as an approximate index can.
+    pub recall_threshold: f64,
+    /// F64 values and vector components read back from the instance count
+    /// as equal when they differ by no more than either tolerance.
+    pub relative_tolerance: f64,
+    pub absolute_tolerance: f64,
+    pub theme: Theme,
+    /// Plain text for screen readers: results are spelled out as words
+    /// rather than colours, and the screen is never cleared.
+    pub accessible: bool,
+}
+
+/// What parsing the command line asked helixir to do.
+pub enum Invocation {
+    Run(Args),
+    Help,
+}
+
+/// Command line flags, before they are layered over the other sources.
+pub struct Args {
+    project: Option<PathBuf>,
+    settings: Settings,
+}
+
+/// One layer of settings. Unset fields fall through to the next layer.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Settings {
+    endpoint: Option<String>,
+    api_key: Option<String>,
+    instance: Option<String>,
+    helix_bin: Option<PathBuf>,
+    deploy_timeout: Option<u64>,
+    strict: Option<bool>,
+    search_metric: Option<SearchMetric>,
+    recall_threshold: Option<f64>,
+    relative_tolerance: Option<f64>,
+    absolute_tolerance: Option<f64>,
+    theme: Option<Theme>,
+    accessible: Option<bool>,
+}
+
+impl Settings {
+    fn or(self, fallback: Settings) -> Settings {
+        Settings {
+            endpoint: self.endpoint.or(fallback.endpoint),
+            api_key: self.api_key.or(fallback.api_key),
+            instance: self.instance.or(fallback.instance),
+            helix_bin: self.helix_bin.or(fallback.helix_bin),
+            deploy_timeout: self.deploy_timeout.or(fallback.deploy_timeout),
+            strict: self.strict.or(fallback.strict),
+            search_metric: self.search_metric.or(fallback.search_metric),
+            recall_threshold: self.recall_threshold.or(fallback.recall_threshold),
+            relative_tolerance: self.relative_tolerance.or(fallback.relative_tolerance),
+            absolute_tolerance: self.absolute_tolerance.or(fallback.absolute_tolerance),
+            theme: self.theme.or(fallback.theme),
+            accessible: self.accessible.or(fallback.accessible),
+        }
+    }
+
+    fn from_env() -> Result<Settings, String> {
+        let var = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+
+        Ok(Settings {
+            endpoint: var("HELIXIR_ENDPOINT"),
+            api_key: var("HELIXIR_API_KEY"),
+            instance: var("HELIXIR_INSTANCE"),
+            helix_bin: var("HELIXIR_HELIX_BIN").map(PathBuf::from),
+            deploy_timeout: var("HELIXIR_DEPLOY_TIMEOUT")
+                .map(|value| parse_seconds("HELIXIR_DEPLOY_TIMEOUT", &value))
+                .transpose()?,
+            strict: var("HELIXIR_STRICT")
+                .map(|value| parse_bool("HELIXIR_STRICT", &value))
+                .transpose()?,
+            search_metric: var("HELIXIR_SEARCH_METRIC")
+                .map(|value| parse_metric("HELIXIR_SEARCH_METRIC", &value))
+                .transpose()?,
+            recall_threshold: var("HELIXIR_RECALL_THRESHOLD")
+                .map(|value| parse_fraction("HELIXIR_RECALL_THRESHOLD", &value))
+                .transpose()?,
+            relative_tolerance: var("HELIXIR_RELATIVE_TOLERANCE")
+                .map(|value| parse_tolerance("HELIXIR_RELATIVE_TOLERANCE", &value))
+                .transpose()?,
+            absolute_tolerance: var("HELIXIR_ABSOLUTE_TOLERANCE")
+                .map(|value| parse_tolerance("HELIXIR_ABSOLUTE_TOLERANCE", &value))
+                .transpose()?,
+            theme: var("HELIXIR_THEME")
+                .map(|value| parse_theme("HELIXIR_THEME", &value))
+                .transpose()?,
+            accessible: var("HELIXIR_ACCESSIBLE")
+                .map(|value| parse_bool("HELIXIR_ACCESSIBLE", &value))
+                .transpose()?,
+        })
+    }
+
+    fn from_file() -> Result<Settings, String> {
+        let Some(path) = config_file_path() else {
+            return Ok(Settings::default());
+        };
+        let Ok(content) = std::fs::read_to_string(&path) else {
+            return Ok(Settings::default());
+        };
+
+        toml::from_str(&content).map_err(|e| format!("Invalid {}: {}", path.display(), e))
+    }
+}
+
+impl Args {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Invocation, String> {
+        let mut project = None;
+        let mut settings = Settings::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            if arg == "-h" || arg == "--help" {
+                return Ok(Invocation::Help);
+            }
+            if arg == "--strict" {
+                settings.strict = Some(true);
+                continue;
+            }
+            if arg == "--accessible" {
+                settings.accessible = Some(true);
+                continue;
+            }
+
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+                None => (arg, None),
+            };
+            let mut value = || {
+                inline_value
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| format!("{} needs a value", flag))
+            };
+
+            match flag.as_str() {
+                "--project" => project = Some(PathBuf::from(value()?)),
+                "--endpoint" => settings.endpoint = Some(value()?),
+                "--api-key" => settings.api_key = Some(value()?),
+                "--instance" => settings.instance = Some(value()?),
+                "--helix-bin" => settings.helix_bin = Some(PathBuf::from(value()?)),
+                "--deploy-timeout" => {
+                    settings.deploy_timeout = Some(parse_seconds(&flag, &value()?)?)
+                }
+                "--search-metric" => settings.search_metric = Some(parse_metric(&flag, &value()?)?),
+                "--recall-threshold" => {
+                    settings.recall_threshold = Some(parse_fraction(&flag, &value()?)?)
+                }
+                "--relative-tolerance" => {
+                    settings.relative_tolerance = Some(parse_tolerance(&flag, &value()?)?)
+                }
+                "--absolute-tolerance" => {
+                    settings.absolute_tolerance = Some(parse_tolerance(&flag, &value()?)?)
+                }
+                "--theme" => settings.theme = Some(parse_theme(&flag, &value()?)?),
+                _ => return Err(format!("Unknown option '{}'", flag)),
+            }
+        }
+
+        Ok(Invocation::Run(Args { project, settings }))
+    }
+
+    /// The directory given with `--project`, if any.
+    pub fn project(&self) -> Option<&Path> {
+        self.project.as_deref()
+    }
+}
+
+impl Config {
+    /// Resolves the configuration from, in order of precedence, command line
+    /// flags, `HELIXIR_*` environment variables and the user's config file.
+    /// The instance and endpoint default to what the project's helix.toml defines.
+    pub fn load(args: Args, project: &Project) -> Result<Config, String> {
+        let settings = args
+            .settings
+            .or(Settings::from_env()?)
+            .or(Settings::from_file()?);
+
+        let instance = settings
+            .instance
+            .unwrap_or_else(|| project.default_instance());
+        project.check_instance(&instance)?;
+        let endpoint = settings.endpoint.unwrap_or_else(|| {
+            let port = project.port(&instance).unwrap_or(DEFAULT_PORT);
+            format!("{}:{}", DEFAULT_HOST, port)
+        });
+
+        Ok(Config {
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            api_key: settings.api_key,
+            instance,
+            helix_binary: settings
+                .helix_bin
+                .map(absolute_binary_path)
+                .unwrap_or_else(|| PathBuf::from(DEFAULT_HELIX_BINARY)),
+            deploy_timeout: Duration::from_secs(
+                settings
+                    .deploy_timeout
+                    .unwrap_or(DEFAULT_DEPLOY_TIMEOUT_SECS),
+            ),
+            strict: settings.strict.unwrap_or(false),
+            search_metric: settings.search_metric.unwrap_or_default(),
+            recall_threshold: match settings.recall_threshold {
+                // config.toml values skip parse_fraction
+                Some(threshold) if !(0.0..=1.0).contains(&threshold) => {
+                    return Err(format!(
+                        "recall_threshold must be between 0 and 1, got {}",
+                        threshold
+                    ));
+                }
+                threshold => threshold.unwrap_or(DEFAULT_RECALL_THRESHOLD),
+            },
+            relative_tolerance: checked_tolerance(
+                "relative_tolerance",
+                settings.relative_tolerance,
+                DEFAULT_RELATIVE_TOLERANCE,
+            )?,
+            absolute_tolerance: checked_tolerance(
+                "absolute_tolerance",
+                settings.absolute_tolerance,
+                DEFAULT_ABSOLUTE_TOLERANCE,
+            )?,
+            // https://no-color.org, which `colored` follows for all styling anyway
+            theme: if std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+                Theme::Monochrome
+            } else {
+                settings.theme.unwrap_or_default()
+            },
+            accessible: settings.accessible.unwrap_or(false),
+        })
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            endpoint: format!("{}:{}", DEFAULT_HOST, DEFAULT_PORT),
+            api_key: None,
+            instance: Project::DEFAULT_INSTANCE.to_string(),
+            helix_binary: PathBuf::from(DEFAULT_HELIX_BINARY),
+            deploy_timeout: Duration::from_secs(DEFAULT_DEPLOY_TIMEOUT_SECS),
+            strict: false,
+            search_metric: SearchMetric::default(),
+            recall_threshold: DEFAULT_RECALL_THRESHOLD,
+            relative_tolerance: DEFAULT_RELATIVE_TOLERANCE,
+            absolute_tolerance: DEFAULT_ABSOLUTE_TOLERANCE,
+            theme: Theme::default(),
+            accessible: false,
+        }
+    }
+}
+
+/// Makes `config` the configuration returned by [`config`]. Only the first
+/// call has any effect.
+pub fn init(config: Config) {
+    let _ = CONFIG.set(config);
+}
+
+pub fn config() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+fn config_file_path() -> Option<PathBuf> {
+    let home = std::env::var_os("HOME").filter(|home| !home.is_empty())?;
+    Some(PathBuf::from(home).join(".config/helixir/config.toml"))
+}
+
+/// helix commands run in the project root, so a relative path like
+/// `./bin/helix` is pinned to the directory helixir was started from.
+/// Bare names are left alone to be looked up on PATH.
+fn absolute_binary_path(path: PathBuf) -> PathBuf {
+    if path.is_absolute() || path.components().count() == 1 {
+        return path;
+    }
+    std::path::absolute(&path).unwrap_or(path)
+}
+
+fn parse_bool(name: &str, value: &str) -> Result<bool, String> {
+    match value.trim().to_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Ok(true),
+        "0" | "false" | "no" | "off" => Ok(false),
+        _ => Err(format!("{} must be true or false, got '{}'", name, value)),
+    }
+}
+
+fn parse_seconds(name: &str, value: &str) -> Result<u64, String> {
+    value.trim().parse().map_err(|_| {
+        format!(
+            "{} must be a whole number of seconds, got '{}'",
+            name, value
+        )
+    })
+}
+
+fn parse_metric(name: &str, value: &str) -> Result<SearchMetric, String> {
+    match value.trim().to_lowercase().as_str() {
+        "cosine" => Ok(SearchMetric::Cosine),
+        "euclidean" => Ok(SearchMetric::Euclidean),
+        _ => Err(format!(
+            "{} must be cosine or euclidean, got '{}'",
+            name, value
+        )),
+    }
+}
+
+fn parse_theme(name: &str, value: &str) -> Result<Theme, String> {
+    match value.trim().to_lowercase().as_str() {
+        "dark" => Ok(Theme::Dark),
+        "light" => Ok(Theme::Light),
+        "high-contrast" => Ok(Theme::HighContrast),
+        "monochrome" => Ok(Theme::Monochrome),
+        _ => Err(format!(
+            "{} must be dark, light, high-contrast or monochrome, got '{}'",
+            name, value
+        )),
+    }
+}
+
+fn parse_fraction(name: &str, value: &str) -> Result<f64, String> {
+    value
+        .trim()
+        .parse()
+        .ok()
+        .filter(|fraction| (0.0..=1.0).contains(fraction))
+        .ok_or_else(|| format!("{} must be between 0 and 1, got '{}'", name, value))
+}
+
+fn parse_tolerance(name: &str, value: &str) -> Result<f64, String> {
+    value
+        .trim()
+        .parse()
+        .ok()
+        .filter(|tolerance: &f64| tolerance.is_finite() && *tolerance >= 0.0)
+        .ok_or_else(|| format!("{} must be a number of at least 0, got '{}'", name, value))
+}
+
+/// config.toml values skip parse_tolerance.
+fn checked_tolerance(name: &str, tolerance: Option<f64>, default: f64) -> Result<f64, String> {
+    match tolerance {
+        Some(tolerance) if !tolerance.is_finite() || tolerance < 0.0 => Err(format!(
+            "{} must be a number of at least 0, got {}",
+            name, tolerance
+        )),
+        tolerance => Ok(tolerance.unwrap_or(default)),
+    }
+}
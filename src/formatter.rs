@@ -1,19 +1,151 @@
+use std::io::IsTerminal;
+use std::sync::{LazyLock, Mutex};
+
 use colored::*;
-use textwrap::{Options, wrap};
+use pulldown_cmark::{
+    CodeBlockKind, Event, HeadingLevel, Options as MdOptions, Parser, Tag, TagEnd,
+};
+use regex::Regex;
+use serde_json::Value;
+use terminal_size::{Width, terminal_size};
+
+use crate::config::config;
+use crate::lexer::{TokenKind, contains_hql, tokenize};
+use crate::theme::{Themed, Tone};
+use crate::validation::values_match;
+
+/// Lesson types and query names, highlighted wherever lesson text mentions them.
+const LESSON_IDENTIFIERS: &[&str] = &[
+    "Country_to_Capital",
+    "getCountryNames",
+    "getContinentByName",
+    "getCountryByName",
+    "getCityByName",
+    "getAllContinents",
+    "getAllCities",
+    "getCountriesInContinent",
+    "getCitiesInContinent",
+    "Out",
+    "getAllCountries",
+    "createContinent",
+    "createCountry",
+    "createCity",
+    "Continent_to_Country",
+    "Country_to_City",
+    "CityDescription",
+    "City_to_Embedding",
+    "Continent",
+    "Country",
+    "City",
+    "city",
+    "country",
+    "continent",
+    "vector",
+    "AddN",
+    "AddE",
+    "AddV",
+    "countCapitals",
+    "updateDescription",
+    "deleteCity",
+    "getCapital",
+    "getCountriesByGdp",
+    "getCountriesWithCapitals",
+    "deleteCountry",
+    "getContinent",
+    "getCountriesByCurrency",
+    "getContinentCities",
+    "getCitiesInCountry",
+    "updatePopGdp",
+    "getCity",
+    "getCountriesByCurrPop",
+    "setCapital",
+    "updateCurrency",
+    "getCountry",
+    "getCountriesByPopulation",
+    "deleteCapital",
+    "embedDescription",
+    "updateCapital",
+    "getCountriesByPopGdp",
+    "getCountryByCityCnt",
+    "searchDescriptions",
+];
+
+static IDENTIFIERS: LazyLock<Regex> = LazyLock::new(|| {
+    let mut names = LESSON_IDENTIFIERS.to_vec();
+    names.sort_by_key(|name| std::cmp::Reverse(name.len()));
+    let alternatives: Vec<String> = names.iter().map(|name| regex::escape(name)).collect();
+    Regex::new(&format!(r"\b(?:{})\b", alternatives.join("|")))
+        .expect("identifier pattern is valid")
+});
+
+/// Result tags, the words that stand in for them in accessibility mode and
+/// their colour otherwise.
+const STATUS_TAGS: &[(&str, &str, Tone)] = &[
+    ("[CORRECT]", "PASS:", Tone::Green),
+    ("[INCORRECT]", "FAIL:", Tone::Red),
+    ("[OK]", "PASS:", Tone::Green),
+    ("[ERROR]", "ERROR:", Tone::Red),
+    ("[WARNING]", "WARNING:", Tone::Yellow),
+];
+
+/// Narrower than this, wrapping leaves too little room for the text itself.
+const MIN_WIDTH: usize = 20;
+
+/// The lesson screen drawn last, so it can be laid out again when the
+/// terminal is resized.
+static LAST_SCREEN: Mutex<Option<LessonScreen>> = Mutex::new(None);
+
+struct LessonScreen {
+    title: String,
+    lesson_id: usize,
+    instructions: String,
+    output_messages: Vec<String>,
+}
 
 pub struct HelixFormatter {
-    #[allow(dead_code)]
-    terminal_width: usize,
+    /// Set when stdout is not a terminal, e.g. piped into a file, and in
+    /// accessibility mode. Output is then neither wrapped nor decorated.
+    plain: bool,
+    accessible: bool,
 }
 
 impl HelixFormatter {
     pub fn new() -> Self {
-        Self { terminal_width: 80 }
+        Self {
+            plain: !std::io::stdout().is_terminal() || config().accessible,
+            accessible: config().accessible,
+        }
+    }
+
+    /// A result tag such as `[CORRECT]`, coloured, or spelled out as a word
+    /// in accessibility mode so the result does not rest on its colour.
+    pub fn status_tag(&self, tag: &str) -> String {
+        match STATUS_TAGS.iter().find(|(known, _, _)| *known == tag) {
+            Some((_, word, _)) if self.accessible => word.to_string(),
+            Some((_, _, tone)) => tag.themed(*tone).bold().to_string(),
+            None => tag.to_string(),
+        }
+    }
+
+    /// The current width of the terminal, measured on every call so each
+    /// screen is laid out for the size it is drawn at. `None` in the plain
+    /// layout, where lines are left for the reader to wrap.
+    fn width(&self) -> Option<usize> {
+        if self.plain {
+            return None;
+        }
+        let width = terminal_size().map_or(80, |(Width(width), _)| width as usize);
+        Some(width.max(MIN_WIDTH))
+    }
+
+    /// A horizontal rule of up to `length` characters, or `None` in the
+    /// plain layout.
+    fn rule(&self, symbol: &str, length: usize) -> Option<String> {
+        self.width().map(|width| symbol.repeat(length.min(width)))
     }
+
     pub fn display_lesson(&self, title: &str, lesson_id: usize, instructions: &str) {
-        self.print_lesson_header(title, lesson_id);
-        self.print_lesson_content(instructions);
-        self.print_commands();
+        self.display_lesson_with_output(title, lesson_id, instructions, &[]);
     }
     pub fn display_lesson_with_output(
         &self,
@@ -22,6 +154,14 @@ impl HelixFormatter {
         instructions: &str,
         output_messages: &[String],
     ) {
+        if let Ok(mut screen) = LAST_SCREEN.lock() {
+            *screen = Some(LessonScreen {
+                title: title.to_string(),
+                lesson_id,
+                instructions: instructions.to_string(),
+                output_messages: output_messages.to_vec(),
+            });
+        }
         self.print_lesson_header(title, lesson_id);
         self.print_lesson_content(instructions);
 
@@ -31,26 +171,50 @@ impl HelixFormatter {
 
         self.print_commands();
     }
+
+    /// Draws the last lesson screen again at the current terminal width.
+    pub fn redraw(&self) {
+        let screen = LAST_SCREEN.lock().ok().and_then(|mut screen| screen.take());
+        if let Some(screen) = screen {
+            self.display_lesson_with_output(
+                &screen.title,
+                screen.lesson_id,
+                &screen.instructions,
+                &screen.output_messages,
+            );
+        }
+    }
+
+    /// Wraps a styled line to the terminal width, continuing it `indent`
+    /// columns in.
+    fn wrap(&self, line: &str, indent: usize) -> Vec<String> {
+        wrap_line(line, self.width(), indent)
+    }
+
+    fn print_wrapped(&self, line: &str, indent: usize) {
+        for line in self.wrap(line, indent) {
+            println!("{}", line);
+        }
+    }
+
     fn print_output_section(&self, messages: &[String]) {
         println!();
-        println!("{}", "OUTPUT".truecolor(238, 212, 159).bold());
-        println!("{}", "─".repeat(50).truecolor(238, 212, 159));
+        println!("{}", "OUTPUT".themed(Tone::Yellow).bold());
+        if let Some(rule) = self.rule("─", 50) {
+            println!("{}", rule.themed(Tone::Yellow));
+        }
 
         for message in messages {
-            if message.contains("[INCORRECT]") {
-                let colored_message = message.replace(
-                    "[INCORRECT]",
-                    &"[INCORRECT]".truecolor(237, 135, 150).bold().to_string(),
-                );
-                println!("{}", colored_message);
-            } else if message.contains("[ERROR]") {
-                let colored_message = message.replace(
-                    "[ERROR]",
-                    &"[ERROR]".truecolor(237, 135, 150).bold().to_string(),
-                );
-                println!("{}", colored_message);
-            } else {
-                println!("{}", message);
+            let message = STATUS_TAGS
+                .iter()
+                .fold(message.clone(), |message, (tag, _, _)| {
+                    message.replace(tag, &self.status_tag(tag))
+                });
+            // continuation lines line up under the text they continue, so
+            // diffs and listings keep their structure
+            for line in message.lines() {
+                let indent = line.len() - line.trim_start().len();
+                self.print_wrapped(line, indent + 2);
             }
         }
         println!();
@@ -58,258 +222,152 @@ impl HelixFormatter {
 
     fn print_lesson_header(&self, title: &str, lesson_id: usize) {
         let header_text = format!("Lesson {}: {}", lesson_id, title);
-        let border_length = std::cmp::max(header_text.len(), 50);
-        let border = "═".repeat(border_length);
+        let border = self.rule("═", std::cmp::max(header_text.chars().count(), 50));
 
         println!();
-        println!("{}", border.truecolor(145, 215, 227).bold());
-        println!("{}", header_text.truecolor(202, 211, 245).bold());
-        println!("{}", border.truecolor(145, 215, 227).bold());
+        if let Some(border) = &border {
+            println!("{}", border.themed(Tone::Sky).bold());
+        }
+        self.print_wrapped(&header_text.themed(Tone::Text).bold().to_string(), 0);
+        if let Some(border) = &border {
+            println!("{}", border.themed(Tone::Sky).bold());
+        }
         println!();
     }
     fn print_lesson_content(&self, content: &str) {
-        let formatted_content = self.parse_markdown(content);
-        let paragraphs: Vec<&str> = formatted_content.split("\n\n").collect();
-
-        for paragraph in paragraphs {
-            if paragraph.trim().is_empty() {
-                continue;
-            }
-
-            self.format_paragraph_simple(paragraph);
-            println!();
+        for line in self.render_markdown(content) {
+            println!("{}", line);
         }
     }
 
-    fn format_paragraph_simple(&self, paragraph: &str) {
-        let lines: Vec<&str> = paragraph.split('\n').collect();
-
-        for line in lines {
-            let trimmed = line.trim();
-
-            if trimmed.is_empty() {
-                continue;
-            }
-
-            if trimmed.starts_with("▶ ") {
-                println!("{}", trimmed.truecolor(145, 215, 227).bold());
-            } else if trimmed.starts_with("- ") {
-                let parts: Vec<&str> = trimmed.split("- ").collect();
-                if parts.len() > 1 {
-                    println!("- {}", parts[1].truecolor(202, 211, 245));
-                } else {
-                    println!("{}", trimmed.truecolor(202, 211, 245));
+    /// Renders lesson markdown to terminal lines. Single newlines are kept as
+    /// line breaks, as the lesson texts rely on them, and each block is
+    /// followed by a blank line.
+    fn render_markdown(&self, text: &str) -> Vec<String> {
+        let mut writer = MarkdownWriter {
+            width: self.width(),
+            plain: self.plain,
+            ..MarkdownWriter::default()
+        };
+        let mut after_paragraph = false;
+
+        for (event, range) in
+            Parser::new_ext(text, MdOptions::ENABLE_STRIKETHROUGH).into_offset_iter()
+        {
+            let follows_paragraph = std::mem::replace(
+                &mut after_paragraph,
+                matches!(event, Event::End(TagEnd::Paragraph)),
+            );
+            match event {
+                // a list straight after a paragraph line stays attached to it
+                Event::Start(tag @ Tag::List(_)) => {
+                    if writer.lists.is_empty()
+                        && follows_paragraph
+                        && !text[..range.start]
+                            .trim_end_matches([' ', '\t'])
+                            .ends_with("\n\n")
+                        && writer.lines.last().is_some_and(|line| line.is_empty())
+                    {
+                        writer.lines.pop();
+                    }
+                    writer.start(tag)
                 }
-            } else {
-                println!("{}", trimmed.truecolor(202, 211, 245));
+                Event::Start(tag) => writer.start(tag),
+                // highlighted as a whole, as parameters are declared in the header
+                Event::Text(text) if writer.in_code_block => writer.code.push_str(&text),
+                Event::End(TagEnd::CodeBlock) => {
+                    let code = std::mem::take(&mut writer.code);
+                    let code = code.trim_end_matches('\n');
+                    let styled: Vec<String> =
+                        if ["", "hql", "hx"].contains(&writer.code_language.as_str()) {
+                            let highlighted = self.highlight_hql(code);
+                            highlighted.split('\n').map(str::to_string).collect()
+                        } else {
+                            code.split('\n')
+                                .map(|line| line.themed(Tone::Text).to_string())
+                                .collect()
+                        };
+                    for (index, line) in styled.iter().enumerate() {
+                        if index > 0 {
+                            writer.break_line();
+                        }
+                        writer.current.push_str(line);
+                    }
+                    writer.end(TagEnd::CodeBlock)
+                }
+                Event::End(tag) => writer.end(tag),
+                Event::Text(text) => {
+                    writer.link_text(&text);
+                    let styled = self.highlight_identifiers(&text, |plain| writer.paint(plain));
+                    writer.current.push_str(&styled);
+                }
+                Event::Code(code) => {
+                    writer.link_text(&code);
+                    writer
+                        .current
+                        .push_str(&code.themed(Tone::Yellow).to_string())
+                }
+                Event::SoftBreak | Event::HardBreak => writer.break_line(),
+                Event::Rule => {
+                    if let Some(rule) = self.rule("─", 50) {
+                        writer.current = rule.themed(Tone::Yellow).to_string();
+                    }
+                    writer.end_block();
+                }
+                Event::Html(html) | Event::InlineHtml(html) => writer.current.push_str(&html),
+                _ => {}
             }
         }
-    }
-
-    #[allow(dead_code)]
-    fn format_paragraph(&self, paragraph: &str) {
-        let lines: Vec<&str> = paragraph.split('\n').collect();
-
-        for line in lines {
-            let trimmed = line.trim();
+        writer.flush();
 
-            if trimmed.is_empty() {
-                continue;
-            }
-
-            if self.is_code_block(trimmed) {
-                self.print_code_block(trimmed);
-            } else if trimmed.starts_with("- ") || trimmed.starts_with("* ") {
-                self.print_bullet_point(trimmed);
-            } else if trimmed.starts_with("Write ") || trimmed.starts_with("Create ") {
-                self.print_task_instruction(trimmed);
-            } else {
-                self.print_regular_text(trimmed);
-            }
+        while writer.lines.last().is_some_and(|line| line.is_empty()) {
+            writer.lines.pop();
         }
+        writer.lines.push(String::new());
+        writer.lines
     }
 
-    fn is_code_block(&self, line: &str) -> bool {
-        line.contains("QUERY ")
-            || line.contains("N::")
-            || line.contains("E::")
-            || line.contains("V::")
-            || line.contains("AddN")
-            || line.contains("AddE")
-            || line.contains("AddV")
-            || line.contains("helix ")
-            || line.contains("schema.hx")
-            || line.contains("query.hx")
-            || line.contains("::WHERE")
-            || line.contains("::UPDATE")
-            || line.contains("::COUNT")
-            || line.contains("::RANGE")
+    /// A single line of markdown, such as a hint, without the block spacing.
+    fn render_inline(&self, text: &str) -> String {
+        self.render_markdown(text)
+            .into_iter()
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n")
     }
 
-    #[allow(dead_code)]
-    fn print_code_block(&self, line: &str) {
-        let highlighted = self.highlight_helix_syntax(line);
-        println!("{}", highlighted);
-    }
-
-    fn highlight_helix_syntax(&self, code: &str) -> String {
-        let mut result = code.to_string();
-
-        let keywords = [
-            "QUERY", "WHERE", "AND", "OR", "EXISTS", "COUNT", "RANGE", "UPDATE", "DROP", "AddN",
-            "AddE", "AddV", "SearchV", "RETURN",
-        ];
-
-        for keyword in keywords {
-            result = result.replace(
-                keyword,
-                &keyword.truecolor(198, 160, 246).bold().to_string(),
-            );
-        }
-
-        let schema_patterns = ["N::", "E::", "V::"];
-        for pattern in schema_patterns {
-            result = result.replace(
-                pattern,
-                &pattern.truecolor(138, 173, 244).bold().to_string(),
-            );
-        }
-
-        let types = ["String", "I64", "U64", "F64", "ID"];
-        for type_name in types {
-            result = result.replace(type_name, &type_name.truecolor(166, 218, 149).to_string());
+    /// Picks out the names of lesson types and queries in `text`, styling
+    /// the rest with `paint`.
+    fn highlight_identifiers(&self, text: &str, paint: impl Fn(&str) -> String) -> String {
+        let mut result = String::new();
+        let mut last = 0;
+        for found in IDENTIFIERS.find_iter(text) {
+            result.push_str(&paint(&text[last..found.start()]));
+            result.push_str(&found.as_str().themed(Tone::Yellow).bold().to_string());
+            last = found.end();
         }
-
-        let operators = ["::EQ", "::LT", "::GT", "::LTE", "::GTE", "::Out", "::In"];
-        for op in operators {
-            result = result.replace(op, &op.truecolor(238, 212, 159).to_string());
-        }
-
-        if result.contains(".hx") {
-            result = result.replace(".hx", &".hx".truecolor(139, 213, 202).to_string());
-        }
-        if result.contains("helix ") {
-            result = result.replace(
-                "helix ",
-                &"helix ".truecolor(237, 135, 150).bold().to_string(),
-            );
-        }
-
+        result.push_str(&paint(&text[last..]));
         result
     }
 
-    #[allow(dead_code)]
-    fn print_bullet_point(&self, line: &str) {
-        let content = line.trim_start_matches("- ").trim_start_matches("* ");
-        let formatted_content = self.parse_markdown(content);
-        println!("{} {}", "▶".bright_blue().bold(), formatted_content);
-    }
-
-    #[allow(dead_code)]
-    fn print_task_instruction(&self, line: &str) {
-        let formatted_line = self.parse_markdown(line);
-        if line.starts_with("Write ") {
-            println!("{}", formatted_line.bright_yellow().bold());
-        } else if line.starts_with("Create ") {
-            println!("{}", formatted_line.bright_green().bold());
-        } else {
-            println!("{}", formatted_line.bright_cyan().bold());
-        }
-    }
-
-    #[allow(dead_code)]
-    fn print_regular_text(&self, line: &str) {
-        let formatted_line = self.parse_markdown(line);
-        let wrapped = wrap(&formatted_line, Options::new(self.terminal_width));
-        for wrapped_line in wrapped {
-            println!("{}", wrapped_line);
-        }
-    }
-
-    fn parse_markdown(&self, text: &str) -> String {
-        let mut result = text.to_string();
-        let mut replacements = Vec::new();
-
-        for node_type in &[
-            "Country_to_Capital",
-            "getCountryNames",
-            "getContinentByName",
-            "getCountryByName",
-            "getCityByName",
-            "getAllContinents",
-            "getAllCities",
-            "getCountriesInContinent",
-            "getCitiesInContinent",
-            "Out",
-            "getAllCountries",
-            "createContinent",
-            "createCountry",
-            "createCity",
-            "Continent_to_Country",
-            "Country_to_City",
-            "CityDescription",
-            "City_to_Embedding",
-            "Continent",
-            "Country",
-            "City",
-            "city",
-            "country",
-            "continent",
-            "vector",
-            "AddN",
-            "AddE",
-            "AddV",
-            "countCapitals",
-            "updateDescription",
-            "deleteCity",
-            "getCapital",
-            "getCountriesByGdp",
-            "getCountriesWithCapitals",
-            "deleteCountry",
-            "getContinent",
-            "getCountriesByCurrency",
-            "getContinentCities",
-            "getCitiesInCountry",
-            "updatePopGdp",
-            "getCity",
-            "getCountriesByCurrPop",
-            "setCapital",
-            "updateCurrency",
-            "getCountry",
-            "getCountriesByPopulation",
-            "deleteCapital",
-            "embedDescription",
-            "updateCapital",
-            "getCountriesByPopGdp",
-            "getCountryByCityCnt",
-            "searchDescriptions",
-        ] {
-            let pattern = format!(r"\b{}\b", regex::escape(node_type));
-            if let Ok(regex) = regex::Regex::new(&pattern) {
-                if regex.is_match(&result) {
-                    let placeholder = format!("__HIGHLIGHT_{}__", replacements.len());
-                    let highlighted = node_type.truecolor(238, 212, 159).bold().to_string();
-                    replacements.push((placeholder.clone(), highlighted));
-                    result = regex.replace_all(&result, &placeholder).to_string();
+    /// Colours HQL token by token. Whitespace is left unstyled, so the
+    /// result can be split into lines like the source.
+    fn highlight_hql(&self, code: &str) -> String {
+        tokenize(code)
+            .into_iter()
+            .map(|token| match token.kind {
+                TokenKind::Whitespace => token.text.to_string(),
+                TokenKind::Keyword => token.text.themed(Tone::Mauve).bold().to_string(),
+                TokenKind::Step => token.text.themed(Tone::Yellow).to_string(),
+                TokenKind::TypeName => token.text.themed(Tone::Green).to_string(),
+                TokenKind::Parameter => token.text.themed(Tone::Peach).to_string(),
+                TokenKind::Literal => token.text.themed(Tone::Teal).to_string(),
+                TokenKind::Comment => token.text.themed(Tone::Overlay).italic().to_string(),
+                TokenKind::Identifier | TokenKind::Punctuation => {
+                    token.text.themed(Tone::Text).to_string()
                 }
-            }
-        }
-
-        for (placeholder, highlighted) in replacements {
-            result = result.replace(&placeholder, &highlighted);
-        }
-        while let Some(start) = result.find("**") {
-            if let Some(end) = result[start + 2..].find("**") {
-                let end = end + start + 2;
-                let bold_text = &result[start + 2..end];
-                let formatted = bold_text.truecolor(202, 211, 245).bold().to_string();
-                result.replace_range(start..end + 2, &formatted);
-            } else {
-                break;
-            }
-        }
-
-        result
+            })
+            .collect()
     }
 
     pub fn print_hints(&self, hints: &[String]) {
@@ -317,29 +375,34 @@ impl HelixFormatter {
             return;
         }
 
-        println!("{}", "HINTS".truecolor(238, 212, 159).bold());
-        println!("{}", "─".repeat(20).truecolor(238, 212, 159));
+        println!("{}", "HINTS".themed(Tone::Yellow).bold());
+        if let Some(rule) = self.rule("─", 20) {
+            println!("{}", rule.themed(Tone::Yellow));
+        }
 
         for (i, hint) in hints.iter().enumerate() {
             let hint_number = format!("{}.", i + 1);
-            let formatted_hint = if self.is_code_block(hint) {
-                self.highlight_helix_syntax(hint)
+            let formatted_hint = if contains_hql(hint) {
+                self.highlight_hql(hint)
             } else {
-                self.parse_markdown(hint)
+                self.render_inline(hint)
             };
 
-            println!(
+            let line = format!(
                 "{} {}",
-                hint_number.truecolor(238, 212, 159).bold(),
+                hint_number.themed(Tone::Yellow).bold(),
                 formatted_hint
             );
+            self.print_wrapped(&line, hint_number.len() + 1);
         }
         println!();
     }
 
     fn print_commands(&self) {
-        println!("{}", "COMMANDS".truecolor(166, 218, 149).bold());
-        println!("{}", "─".repeat(20).truecolor(166, 218, 149));
+        println!("{}", "COMMANDS".themed(Tone::Green).bold());
+        if let Some(rule) = self.rule("─", 20) {
+            println!("{}", rule.themed(Tone::Green));
+        }
 
         let commands = [
             ("n", "next", "Continue to next lesson"),
@@ -349,16 +412,24 @@ impl HelixFormatter {
             ("g N", "goto", "Go to specific lesson (e.g., 'g 5')"),
             ("p", "progress", "Show lesson progress"),
             ("r", "run-all", "Run all previous lessons"),
+            ("l", "log", "Show the last deploy log"),
+            ("d", "dedupe", "Remove duplicate nodes left by earlier runs"),
+            (
+                "s",
+                "seed",
+                "Reset the database to the start of this lesson",
+            ),
             ("q", "quit", "Exit the program"),
         ];
 
         for (key, cmd, desc) in commands {
-            println!(
+            let line = format!(
                 "{} {} - {}",
-                format!("({})", key).truecolor(166, 218, 149).bold(),
-                cmd.truecolor(202, 211, 245).bold(),
-                desc.truecolor(184, 192, 224)
+                format!("({})", key).themed(Tone::Green).bold(),
+                cmd.themed(Tone::Text).bold(),
+                desc.themed(Tone::Subtext)
             );
+            self.print_wrapped(&line, key.len() + cmd.len() + 6);
         }
         println!();
     }
@@ -370,16 +441,18 @@ impl HelixFormatter {
         println!(
             "{}",
             "  [ A rustling-styled interactive learning tool for"
-                .truecolor(184, 192, 224)
+                .themed(Tone::Subtext)
                 .bold()
         );
-        println!(
-            "{}",
-            "  mastering helix-db ]".truecolor(184, 192, 224).bold()
-        );
+        println!("{}", "  mastering helix-db ]".themed(Tone::Subtext).bold());
     }
 
+    /// The banner is only drawn where it fits; the plain layout and narrow
+    /// terminals go without.
     fn print_ascii_art(&self) {
+        if self.width().is_none_or(|width| width < 48) {
+            return;
+        }
         let helix_lines = [
             r"██╗  ██╗███████╗██╗     ██╗██╗  ██╗██╗██████╗ ",
             r"██║  ██║██╔════╝██║     ██║╚██╗██╔╝██║██╔══██╗",
@@ -390,32 +463,218 @@ impl HelixFormatter {
         ];
 
         for line in helix_lines {
-            println!("  {}", line.truecolor(198, 160, 246).bold());
+            println!("  {}", line.themed(Tone::Mauve).bold());
         }
     }
 
+    /// Renders `actual` against `expected` as a diff: removed fields are the
+    /// ones only `expected` has, added ones only `actual` has, and unchanged
+    /// subtrees are collapsed to a summary.
+    pub fn render_json_diff(&self, expected: &Value, actual: &Value) -> String {
+        let mut lines = vec![format!(
+            "{} {} {}",
+            "Diff:".themed(Tone::Text).bold(),
+            "- expected".themed(Tone::Red),
+            "+ database result".themed(Tone::Green)
+        )];
+        self.diff_value("", expected, actual, 0, &mut lines);
+        lines.join("\n")
+    }
+
+    /// Renders `actual` against `expected` line by line, with both sides
+    /// highlighted as HQL. Lines are paired by their longest common
+    /// subsequence, ignoring indentation.
+    pub fn render_query_diff(&self, expected: &str, actual: &str) -> String {
+        let mut lines = vec![format!(
+            "{} {} {}",
+            "Diff:".themed(Tone::Text).bold(),
+            "- expected".themed(Tone::Red),
+            "+ your query".themed(Tone::Green)
+        )];
+        let expected_lines: Vec<&str> = expected.split('\n').map(str::trim).collect();
+        let actual_lines: Vec<&str> = actual.split('\n').map(str::trim).collect();
+        let expected_styled = self.highlight_hql(expected);
+        let expected_styled: Vec<&str> = expected_styled.split('\n').collect();
+        let actual_styled = self.highlight_hql(actual);
+        let actual_styled: Vec<&str> = actual_styled.split('\n').collect();
+
+        let (rows, columns) = (expected_lines.len(), actual_lines.len());
+        let mut common = vec![vec![0usize; columns + 1]; rows + 1];
+        for row in (0..rows).rev() {
+            for column in (0..columns).rev() {
+                common[row][column] = if expected_lines[row] == actual_lines[column] {
+                    common[row + 1][column + 1] + 1
+                } else {
+                    common[row + 1][column].max(common[row][column + 1])
+                };
+            }
+        }
+
+        let (mut row, mut column) = (0, 0);
+        while row < rows || column < columns {
+            if row < rows && column < columns && expected_lines[row] == actual_lines[column] {
+                lines.push(format!("  {}", actual_styled[column]));
+                row += 1;
+                column += 1;
+            } else if row < rows
+                && (column == columns || common[row + 1][column] >= common[row][column + 1])
+            {
+                lines.push(format!(
+                    "{} {}",
+                    "-".themed(Tone::Red).bold(),
+                    expected_styled[row]
+                ));
+                row += 1;
+            } else {
+                lines.push(format!(
+                    "{} {}",
+                    "+".themed(Tone::Green).bold(),
+                    actual_styled[column]
+                ));
+                column += 1;
+            }
+        }
+        lines.join("\n")
+    }
+
+    fn diff_value(
+        &self,
+        key: &str,
+        expected: &Value,
+        actual: &Value,
+        depth: usize,
+        lines: &mut Vec<String>,
+    ) {
+        let indent = "  ".repeat(depth);
+        match (expected, actual) {
+            (Value::Object(expected_fields), Value::Object(actual_fields))
+                if !values_match(actual, expected) =>
+            {
+                lines.push(format!("  {}{}{{", indent, key));
+                for (name, value) in actual_fields {
+                    let field = format!("\"{}\": ", name);
+                    match expected_fields.get(name) {
+                        Some(expected) => {
+                            self.diff_value(&field, expected, value, depth + 1, lines)
+                        }
+                        None => lines.push(self.diff_line('+', depth + 1, &field, value)),
+                    }
+                }
+                for (name, value) in expected_fields {
+                    if !actual_fields.contains_key(name) {
+                        let field = format!("\"{}\": ", name);
+                        lines.push(self.diff_line('-', depth + 1, &field, value));
+                    }
+                }
+                lines.push(format!("  {}}}", indent));
+            }
+            (Value::Array(expected_items), Value::Array(actual_items))
+                if !values_match(actual, expected) =>
+            {
+                lines.push(format!("  {}{}[", indent, key));
+                let mut unchanged = 0;
+                for index in 0..expected_items.len().max(actual_items.len()) {
+                    let (expected, actual) = (expected_items.get(index), actual_items.get(index));
+                    if let (Some(expected), Some(actual)) = (expected, actual)
+                        && values_match(actual, expected)
+                    {
+                        unchanged += 1;
+                        continue;
+                    }
+                    self.push_unchanged_run(&mut unchanged, depth + 1, lines);
+                    let position = format!("[{}] ", index);
+                    match (expected, actual) {
+                        (Some(expected), Some(actual)) => {
+                            self.diff_value(&position, expected, actual, depth + 1, lines)
+                        }
+                        (Some(expected), None) => {
+                            lines.push(self.diff_line('-', depth + 1, &position, expected))
+                        }
+                        (None, Some(actual)) => {
+                            lines.push(self.diff_line('+', depth + 1, &position, actual))
+                        }
+                        (None, None) => {}
+                    }
+                }
+                self.push_unchanged_run(&mut unchanged, depth + 1, lines);
+                lines.push(format!("  {}]", indent));
+            }
+            _ if values_match(actual, expected) => {
+                let summary = match actual {
+                    Value::Object(fields) if !fields.is_empty() => {
+                        format!("{{ … {} unchanged fields }}", fields.len())
+                    }
+                    Value::Array(items) if !items.is_empty() => {
+                        format!("[ … {} unchanged items ]", items.len())
+                    }
+                    _ => actual.to_string(),
+                };
+                lines.push(format!(
+                    "  {}{}{}",
+                    indent,
+                    key,
+                    summary.themed(Tone::Overlay)
+                ));
+            }
+            (Value::Object(_) | Value::Array(_), _) | (_, Value::Object(_) | Value::Array(_)) => {
+                lines.push(self.diff_line('-', depth, key, expected));
+                lines.push(self.diff_line('+', depth, key, actual));
+            }
+            _ => lines.push(
+                format!("~ {}{}{} → {}", indent, key, expected, actual)
+                    .themed(Tone::Yellow)
+                    .to_string(),
+            ),
+        }
+    }
+
+    fn diff_line(&self, marker: char, depth: usize, key: &str, value: &Value) -> String {
+        let line = format!("{} {}{}{}", marker, "  ".repeat(depth), key, value);
+        match marker {
+            '-' => line.themed(Tone::Red).to_string(),
+            _ => line.themed(Tone::Green).to_string(),
+        }
+    }
+
+    fn push_unchanged_run(&self, unchanged: &mut usize, depth: usize, lines: &mut Vec<String>) {
+        if *unchanged == 0 {
+            return;
+        }
+        let summary = format!(
+            "… {} unchanged item{}",
+            unchanged,
+            if *unchanged == 1 { "" } else { "s" }
+        );
+        lines.push(format!(
+            "  {}{}",
+            "  ".repeat(depth),
+            summary.themed(Tone::Overlay)
+        ));
+        *unchanged = 0;
+    }
+
     pub fn display_validation_result(&self, is_correct: bool, message: &str) {
         println!();
         if is_correct {
-            println!("{}", "[CORRECT]".truecolor(166, 218, 149).bold());
-            println!("{}", message.truecolor(202, 211, 245));
+            println!("{}", self.status_tag("[CORRECT]"));
+            self.print_wrapped(&message.themed(Tone::Text).to_string(), 0);
         } else {
-            println!("{}", "[INCORRECT]".truecolor(237, 135, 150).bold());
-            println!("{}", message.truecolor(237, 135, 150));
+            println!("{}", self.status_tag("[INCORRECT]"));
+            self.print_wrapped(&message.themed(Tone::Red).to_string(), 0);
         }
         println!();
     }
 
     pub fn display_error(&self, error: &str) {
         println!();
-        println!("{}", "[ERROR]".truecolor(237, 135, 150).bold());
-        println!("{}", error.truecolor(237, 135, 150));
+        println!("{}", self.status_tag("[ERROR]"));
+        self.print_wrapped(&error.themed(Tone::Red).to_string(), 0);
         println!();
     }
 
     pub fn display_info(&self, message: &str) {
         println!();
-        println!("{}", message.truecolor(138, 173, 244));
+        self.print_wrapped(&message.themed(Tone::Blue).to_string(), 0);
         println!();
     }
 }
@@ -425,3 +684,193 @@ impl Default for HelixFormatter {
         Self::new()
     }
 }
+
+/// Line-by-line state while rendering markdown events.
+#[derive(Default)]
+struct MarkdownWriter {
+    lines: Vec<String>,
+    current: String,
+    strong: usize,
+    emphasis: usize,
+    strikethrough: usize,
+    heading: Option<HeadingLevel>,
+    /// The target of the open link, and the plain text it has shown so far.
+    link: Option<(String, String)>,
+    /// The next number of each open list, or `None` for bullet lists.
+    lists: Vec<Option<u64>>,
+    in_code_block: bool,
+    /// The text and fence language of the open code block.
+    code: String,
+    code_language: String,
+    /// Where lines wrap, or `None` to leave them whole.
+    width: Option<usize>,
+    /// Drops the decorations of the terminal layout.
+    plain: bool,
+}
+
+impl MarkdownWriter {
+    fn start(&mut self, tag: Tag) {
+        match tag {
+            Tag::Heading { level, .. } => {
+                self.heading = Some(level);
+                if !self.plain {
+                    self.current = "▶ ".themed(Tone::Sky).bold().to_string();
+                }
+            }
+            Tag::Strong => self.strong += 1,
+            Tag::Emphasis => self.emphasis += 1,
+            Tag::Strikethrough => self.strikethrough += 1,
+            Tag::Link { dest_url, .. } => self.link = Some((dest_url.to_string(), String::new())),
+            Tag::List(start) => {
+                // a list right after a paragraph line continues that block
+                self.flush();
+                self.lists.push(start);
+            }
+            Tag::Item => {
+                self.flush();
+                let depth = self.lists.len().saturating_sub(1);
+                let marker = match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        format!("{}.", *number - 1)
+                    }
+                    _ if self.plain => "-".to_string(),
+                    _ => ["-", "•", "◦"][depth % 3].to_string(),
+                };
+                self.current = format!("{}{} ", "  ".repeat(depth), marker.themed(Tone::Sky));
+            }
+            Tag::CodeBlock(kind) => {
+                self.flush();
+                self.in_code_block = true;
+                self.code_language.clear();
+                if let CodeBlockKind::Fenced(language) = kind
+                    && !language.is_empty()
+                {
+                    self.lines
+                        .push(format!("  {}", language.themed(Tone::Overlay)));
+                    self.code_language = language.to_string();
+                }
+                self.current = "  ".to_string();
+            }
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, tag: TagEnd) {
+        match tag {
+            TagEnd::Heading(_) => {
+                self.heading = None;
+                self.end_block();
+            }
+            TagEnd::Strong => self.strong -= 1,
+            TagEnd::Emphasis => self.emphasis -= 1,
+            TagEnd::Strikethrough => self.strikethrough -= 1,
+            TagEnd::Link => {
+                // autolinks already show their target
+                if let Some((url, text)) = self.link.take()
+                    && !text.contains(url.as_str())
+                {
+                    self.current
+                        .push_str(&format!(" ({})", url).themed(Tone::Overlay).to_string());
+                }
+            }
+            TagEnd::Paragraph if !self.lists.is_empty() => self.flush(),
+            TagEnd::Paragraph => self.end_block(),
+            TagEnd::Item => self.flush(),
+            TagEnd::List(_) => {
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.end_block();
+                }
+            }
+            TagEnd::CodeBlock => {
+                self.in_code_block = false;
+                self.end_block();
+            }
+            _ => {}
+        }
+    }
+
+    /// Styles plain text for the inline formatting currently open.
+    fn paint(&self, text: &str) -> String {
+        if text.is_empty() {
+            return String::new();
+        }
+        let mut styled = match (self.heading, &self.link) {
+            (Some(HeadingLevel::H1), _) => text.themed(Tone::Sky).bold(),
+            (Some(_), _) => text.themed(Tone::Blue).bold(),
+            (None, Some(_)) => text.themed(Tone::Blue).underline(),
+            (None, None) => text.themed(Tone::Text),
+        };
+        if self.strong > 0 {
+            styled = styled.bold();
+        }
+        if self.emphasis > 0 {
+            styled = styled.italic();
+        }
+        if self.strikethrough > 0 {
+            styled = styled.strikethrough();
+        }
+        styled.to_string()
+    }
+
+    /// Records `text` as part of the open link, if there is one.
+    fn link_text(&mut self, text: &str) {
+        if let Some((_, link_text)) = &mut self.link {
+            link_text.push_str(text);
+        }
+    }
+
+    /// Starts a new line, keeping list items and code blocks indented.
+    fn break_line(&mut self) {
+        let indent = if self.in_code_block {
+            2
+        } else {
+            self.lists.len() * 2
+        };
+        self.push_current();
+        self.current = " ".repeat(indent);
+    }
+
+    fn flush(&mut self) {
+        if !self.current.trim().is_empty() {
+            self.push_current();
+        }
+        self.current.clear();
+    }
+
+    /// Ends the current line, wrapping it so continuations line up with the
+    /// text of the list item, heading or code block it belongs to.
+    fn push_current(&mut self) {
+        let indent = if self.in_code_block {
+            4
+        } else if self.heading.is_some() && !self.plain {
+            2
+        } else {
+            self.lists.len() * 2
+        };
+        let line = std::mem::take(&mut self.current);
+        self.lines.extend(wrap_line(&line, self.width, indent));
+    }
+
+    fn end_block(&mut self) {
+        self.flush();
+        if self.lines.last().is_some_and(|line| !line.is_empty()) {
+            self.lines.push(String::new());
+        }
+    }
+}
+
+/// Wraps `line` at `width` columns, ignoring colour codes, with every line
+/// after the first indented by `indent`.
+fn wrap_line(line: &str, width: Option<usize>, indent: usize) -> Vec<String> {
+    let Some(width) = width else {
+        return vec![line.to_string()];
+    };
+    let indent = " ".repeat(indent.min(width / 2));
+    let options = textwrap::Options::new(width).subsequent_indent(&indent);
+    textwrap::wrap(line, options)
+        .into_iter()
+        .map(|line| line.into_owned())
+        .collect()
+}
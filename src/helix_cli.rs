@@ -0,0 +1,271 @@
+#[cfg(test)]
+use std::cell::RefCell;
+#[cfg(test)]
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::path::PathBuf;
+use std::process::Stdio;
+
+use async_trait::async_trait;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HelixCommand {
+    Init,
+    Check,
+    Build,
+    Push,
+    Start,
+    Stop,
+    Status,
+}
+
+impl HelixCommand {
+    /// Arguments passed to the helix binary; environment-scoped commands take
+    /// the instance name as their last argument.
+    pub fn args(&self, environment: &str) -> Vec<String> {
+        let (command, scoped) = match self {
+            Self::Init => ("init", false),
+            Self::Check => ("check", false),
+            Self::Build => ("build", true),
+            Self::Push => ("push", true),
+            Self::Start => ("start", true),
+            Self::Stop => ("stop", true),
+            Self::Status => ("status", false),
+        };
+
+        let mut args = vec![command.to_string()];
+        if scoped {
+            args.push(environment.to_string());
+        }
+        args
+    }
+}
+
+/// Receives each line a helix command prints, as soon as it is read.
+pub type OnLine = dyn FnMut(&str);
+
+#[derive(Debug, Clone, Default)]
+pub struct CliOutput {
+    pub success: bool,
+    pub code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// The helix commands helixir drives. Implemented by [`ProcessHelixCli`] for
+/// real use and by [`FakeHelixCli`] to script responses without a helix install.
+#[async_trait(?Send)]
+pub trait HelixCli {
+    async fn run(&self, command: HelixCommand, on_line: &mut OnLine) -> io::Result<CliOutput>;
+
+    /// How the command is shown to the learner, e.g. `helix build dev`.
+    fn command_line(&self, command: HelixCommand) -> String;
+
+    async fn init(&self) -> io::Result<CliOutput> {
+        self.run(HelixCommand::Init, &mut |_| {}).await
+    }
+
+    async fn check(&self) -> io::Result<CliOutput> {
+        self.run(HelixCommand::Check, &mut |_| {}).await
+    }
+
+    async fn build(&self, on_line: &mut OnLine) -> io::Result<CliOutput> {
+        self.run(HelixCommand::Build, on_line).await
+    }
+
+    async fn push(&self, on_line: &mut OnLine) -> io::Result<CliOutput> {
+        self.run(HelixCommand::Push, on_line).await
+    }
+
+    async fn start(&self) -> io::Result<CliOutput> {
+        self.run(HelixCommand::Start, &mut |_| {}).await
+    }
+
+    async fn stop(&self) -> io::Result<CliOutput> {
+        self.run(HelixCommand::Stop, &mut |_| {}).await
+    }
+
+    async fn status(&self) -> io::Result<CliOutput> {
+        self.run(HelixCommand::Status, &mut |_| {}).await
+    }
+}
+
+pub struct ProcessHelixCli {
+    binary: PathBuf,
+    environment: String,
+    /// The project root; helix looks for helix.toml in its working directory.
+    working_dir: PathBuf,
+}
+
+impl ProcessHelixCli {
+    pub fn new(
+        binary: impl Into<PathBuf>,
+        environment: impl Into<String>,
+        working_dir: impl Into<PathBuf>,
+    ) -> Self {
+        Self {
+            binary: binary.into(),
+            environment: environment.into(),
+            working_dir: working_dir.into(),
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl HelixCli for ProcessHelixCli {
+    async fn run(&self, command: HelixCommand, on_line: &mut OnLine) -> io::Result<CliOutput> {
+        // kill_on_drop lets callers cancel a running command by dropping the future
+        let mut child = Command::new(&self.binary)
+            .args(command.args(&self.environment))
+            .current_dir(&self.working_dir)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+
+        let mut stdout_lines =
+            BufReader::new(child.stdout.take().expect("stdout is piped")).split(b'\n');
+        let mut stderr_lines =
+            BufReader::new(child.stderr.take().expect("stderr is piped")).split(b'\n');
+        let mut stdout = String::new();
+        let mut stderr = String::new();
+        let mut stdout_open = true;
+        let mut stderr_open = true;
+
+        while stdout_open || stderr_open {
+            tokio::select! {
+                line = stdout_lines.next_segment(), if stdout_open => match line? {
+                    Some(line) => collect_line(&line, &mut stdout, on_line),
+                    None => stdout_open = false,
+                },
+                line = stderr_lines.next_segment(), if stderr_open => match line? {
+                    Some(line) => collect_line(&line, &mut stderr, on_line),
+                    None => stderr_open = false,
+                },
+            }
+        }
+
+        let status = child.wait().await?;
+        Ok(CliOutput {
+            success: status.success(),
+            code: status.code(),
+            stdout,
+            stderr,
+        })
+    }
+
+    fn command_line(&self, command: HelixCommand) -> String {
+        format!(
+            "{} {}",
+            self.binary.display(),
+            command.args(&self.environment).join(" ")
+        )
+    }
+}
+
+fn collect_line(raw: &[u8], buffer: &mut String, on_line: &mut OnLine) {
+    let line = String::from_utf8_lossy(raw);
+    let line = line.trim_end_matches('\r');
+    on_line(line);
+    buffer.push_str(line);
+    buffer.push('\n');
+}
+
+/// Replays scripted responses in order for each command and records every
+/// call, so deploy and setup flows can be exercised without a helix install.
+#[cfg(test)]
+#[derive(Default)]
+pub struct FakeHelixCli {
+    responses: RefCell<HashMap<HelixCommand, VecDeque<Result<CliOutput, io::ErrorKind>>>>,
+    calls: RefCell<Vec<HelixCommand>>,
+}
+
+#[cfg(test)]
+impl FakeHelixCli {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn respond(self, command: HelixCommand, output: CliOutput) -> Self {
+        self.responses
+            .borrow_mut()
+            .entry(command)
+            .or_default()
+            .push_back(Ok(output));
+        self
+    }
+
+    pub fn succeed(self, command: HelixCommand, stdout: &str) -> Self {
+        self.respond(
+            command,
+            CliOutput {
+                success: true,
+                code: Some(0),
+                stdout: stdout.to_string(),
+                stderr: String::new(),
+            },
+        )
+    }
+
+    pub fn fail(self, command: HelixCommand, stderr: &str) -> Self {
+        self.respond(
+            command,
+            CliOutput {
+                success: false,
+                code: Some(1),
+                stdout: String::new(),
+                stderr: stderr.to_string(),
+            },
+        )
+    }
+
+    /// Makes the command fail to spawn, as if the helix binary were missing.
+    pub fn missing(self, command: HelixCommand) -> Self {
+        self.responses
+            .borrow_mut()
+            .entry(command)
+            .or_default()
+            .push_back(Err(io::ErrorKind::NotFound));
+        self
+    }
+
+    pub fn calls(&self) -> Vec<HelixCommand> {
+        self.calls.borrow().clone()
+    }
+}
+
+#[cfg(test)]
+#[async_trait(?Send)]
+impl HelixCli for FakeHelixCli {
+    async fn run(&self, command: HelixCommand, on_line: &mut OnLine) -> io::Result<CliOutput> {
+        self.calls.borrow_mut().push(command);
+
+        let response = self
+            .responses
+            .borrow_mut()
+            .get_mut(&command)
+            .and_then(VecDeque::pop_front);
+        match response {
+            Some(Ok(output)) => {
+                output
+                    .stdout
+                    .lines()
+                    .chain(output.stderr.lines())
+                    .for_each(&mut *on_line);
+                Ok(output)
+            }
+            Some(Err(kind)) => Err(io::Error::new(kind, "scripted failure")),
+            None => Err(io::Error::other(format!(
+                "no scripted response for {:?}",
+                command
+            ))),
+        }
+    }
+
+    fn command_line(&self, command: HelixCommand) -> String {
+        format!("helix {}", command.args("dev").join(" "))
+    }
+}
@@ -41,10 +41,10 @@ pub struct CountryData {
     pub currency: String,
     pub population: i64,
     pub gdp: f64,
+    #[serde(default)]
     pub label: String,
 }
 
-
 // city lesson
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -84,6 +84,7 @@ pub struct CapitalEdgeData {
     pub id: String,
     pub from_node: String,
     pub to_node: String,
+    #[serde(default)]
     pub label: String,
 }
 
@@ -104,7 +105,9 @@ pub struct CreateDescEmbeddingResult {
 pub struct DescEmbeddingData {
     pub id: String,
     pub data: Vec<f64>,
+    #[serde(default)]
     pub label: String,
+    #[serde(default)]
     pub score: f64,
 }
 
@@ -113,6 +116,7 @@ pub struct CityEmbeddingEdgeData {
     pub id: String,
     pub from_node: String,
     pub to_node: String,
+    #[serde(default)]
     pub label: String,
 }
 
@@ -432,4 +436,3 @@ pub struct DeleteCountryInput {
 pub struct DeleteCountryResult {
     pub success: String,
 }
-
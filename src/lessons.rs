@@ -6,7 +6,6 @@ pub struct Lesson {
     pub title: String,
     pub instructions: String,
     pub hints: Vec<String>,
-    #[allow(dead_code)]
     pub query_name: Option<Vec<String>>,
 }
 
@@ -185,7 +184,7 @@ pub fn get_lesson(lesson_id: usize) -> Lesson {
         20 => Lesson {
             id: 20,
             title: "Semantic Search Vectors".into(),
-            instructions: "Semantic search allows you to go beyond exact matches by comparing the meaning of data. For example, you can find cities with similar descriptions using vector embeddings. By searching against CityDescription vectors, you can retrieve the top-k most semantically similar City nodes to a given input vector. This is especially useful when you want to find cities that share common characteristics or themes, even if their properties don't match exactly.\n\nFor this lesson, you're using fake embeddings to test the semantic search functionality. In a real application, you would use proper embeddings from models like OpenAI or other embedding providers.\n\nWrite a query (**searchDescriptions**) to semantically search a vector against CityDescription vectors and returning the top k City nodes.\n\n**Query Parameters:** vector: [F64], k: I64".into(),
+            instructions: "Semantic search allows you to go beyond exact matches by comparing the meaning of data. For example, you can find cities with similar descriptions using vector embeddings. By searching against CityDescription vectors, you can retrieve the top-k most semantically similar City nodes to a given input vector. This is especially useful when you want to find cities that share common characteristics or themes, even if their properties don't match exactly.\n\nFor this lesson, helixir turns each city's description into a vector with a small offline embedder that hashes words and letter groups, so descriptions with similar wording end up close together. The search vector is made the same way from the text \"capital city of Germany\". In a real application, you would use proper embeddings from models like OpenAI or other embedding providers.\n\nWrite a query (**searchDescriptions**) to semantically search a vector against CityDescription vectors and returning the top k City nodes.\n\n**Query Parameters:** vector: [F64], k: I64".into(),
             hints: vec![
                 "Add this header into your query.hx: QUERY searchDescriptions (vector: [F64], k: I64) =>".into(),
                 "Use SearchV<CityDescription>(vector, k) to perform semantic search".into(),
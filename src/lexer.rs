@@ -0,0 +1,337 @@
+use std::collections::HashSet;
+
+const KEYWORDS: &[&str] = &[
+    "QUERY", "RETURN", "DROP", "FOR", "IN", "AND", "OR", "NOT", "EXISTS", "AddN", "AddE", "AddV",
+    "SearchV", "N", "E", "V", "INDEX", "UNIQUE", "DEFAULT", "NOW", "_",
+];
+
+/// Keywords of schema definitions, which only count as such before a colon
+/// so that prose like "To do this" is left alone.
+const FIELD_KEYWORDS: &[&str] = &["From", "To", "Properties"];
+
+const TYPE_NAMES: &[&str] = &[
+    "String", "ID", "Date", "Boolean", "I8", "I16", "I32", "I64", "U8", "U16", "U32", "U64",
+    "U128", "F32", "F64",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Keyword,
+    /// A traversal step such as `::Out` or `::WHERE`, including the `::`.
+    Step,
+    TypeName,
+    /// A parameter of the enclosing query, where it is declared and used.
+    Parameter,
+    Identifier,
+    /// Strings, numbers, `true` and `false`.
+    Literal,
+    Comment,
+    Punctuation,
+    Whitespace,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Token<'a> {
+    pub kind: TokenKind,
+    pub text: &'a str,
+}
+
+/// Splits HQL into tokens that together cover all of `source`, so callers
+/// can rebuild it piece by piece. Anything that is not HQL, such as prose
+/// around a snippet, comes out as identifiers and punctuation.
+pub fn tokenize(source: &str) -> Vec<Token<'_>> {
+    let mut lexer = Lexer {
+        source,
+        position: 0,
+        tokens: Vec::new(),
+        parameters: HashSet::new(),
+        header_depth: None,
+    };
+    while lexer.position < source.len() {
+        lexer.next_token();
+    }
+    lexer.tokens
+}
+
+/// Whether `text` contains HQL keywords or traversal steps.
+pub fn contains_hql(text: &str) -> bool {
+    tokenize(text)
+        .iter()
+        .any(|token| matches!(token.kind, TokenKind::Keyword | TokenKind::Step))
+}
+
+struct Lexer<'a> {
+    source: &'a str,
+    position: usize,
+    tokens: Vec<Token<'a>>,
+    /// Parameters declared by the current query's header.
+    parameters: HashSet<&'a str>,
+    /// Bracket depth inside the current QUERY header's parameter list.
+    header_depth: Option<usize>,
+}
+
+impl<'a> Lexer<'a> {
+    fn rest(&self) -> &'a str {
+        &self.source[self.position..]
+    }
+
+    fn push(&mut self, kind: TokenKind, length: usize) {
+        let text = &self.source[self.position..self.position + length];
+        self.position += length;
+        self.tokens.push(Token { kind, text });
+    }
+
+    /// The last token that is not whitespace or a comment.
+    fn previous(&self, skip: usize) -> Option<Token<'a>> {
+        self.tokens
+            .iter()
+            .rev()
+            .filter(|token| !matches!(token.kind, TokenKind::Whitespace | TokenKind::Comment))
+            .nth(skip)
+            .copied()
+    }
+
+    fn next_token(&mut self) {
+        let rest = self.rest();
+        let first = rest.chars().next().unwrap_or_default();
+
+        if first.is_whitespace() {
+            let length = rest
+                .find(|c: char| !c.is_whitespace())
+                .unwrap_or(rest.len());
+            self.push(TokenKind::Whitespace, length);
+        } else if rest.starts_with("//") {
+            let length = rest.find('\n').unwrap_or(rest.len());
+            self.push(TokenKind::Comment, length);
+        } else if first == '"' {
+            self.push(TokenKind::Literal, string_length(rest));
+        } else if first.is_ascii_digit() {
+            self.push(TokenKind::Literal, number_length(rest));
+        } else if let Some(after) = rest.strip_prefix("::")
+            && after.starts_with(is_identifier_start)
+        {
+            self.push(TokenKind::Step, 2 + identifier_length(after));
+        } else if is_identifier_start(first) {
+            self.identifier(identifier_length(rest));
+        } else {
+            let length = ["<-", "=>", "::"]
+                .iter()
+                .find(|symbol| rest.starts_with(*symbol))
+                .map_or(first.len_utf8(), |symbol| symbol.len());
+            self.track_header(&rest[..length]);
+            self.push(TokenKind::Punctuation, length);
+        }
+    }
+
+    fn identifier(&mut self, length: usize) {
+        let word = &self.rest()[..length];
+        let after = self.rest()[length..].trim_start_matches([' ', '\t']);
+        let previous = self.previous(0).map(|token| token.text);
+
+        // `N::Country` in a schema, rather than a traversal step
+        if ["N", "E", "V"].contains(&word) && self.rest()[length..].starts_with("::") {
+            self.push(TokenKind::Keyword, length + 2);
+            return;
+        }
+
+        let kind = if word == "QUERY" {
+            self.parameters.clear();
+            TokenKind::Keyword
+        } else if self.header_depth.is_some() && after.starts_with(':') {
+            self.parameters.insert(word);
+            TokenKind::Parameter
+        } else if FIELD_KEYWORDS.contains(&word) && after.starts_with(':') {
+            TokenKind::Keyword
+        } else if word == "true" || word == "false" {
+            TokenKind::Literal
+        } else if KEYWORDS.contains(&word) {
+            TokenKind::Keyword
+        } else if TYPE_NAMES.contains(&word)
+            || previous == Some("<")
+            || previous.is_some_and(|text| text.ends_with("::") && text.len() == 3)
+            || (previous == Some(":")
+                && self
+                    .previous(1)
+                    .is_some_and(|token| FIELD_KEYWORDS.contains(&token.text)))
+        {
+            TokenKind::TypeName
+        } else if self.parameters.contains(word) {
+            TokenKind::Parameter
+        } else {
+            TokenKind::Identifier
+        };
+        self.push(kind, length);
+    }
+
+    /// Follows the parentheses after `QUERY name` so the names declared in
+    /// them can be told apart from properties later on.
+    fn track_header(&mut self, symbol: &str) {
+        match (symbol, self.header_depth) {
+            ("(", None) => {
+                let starts_header = self.previous(1).is_some_and(|token| token.text == "QUERY")
+                    && self
+                        .previous(0)
+                        .is_some_and(|token| token.kind == TokenKind::Identifier);
+                if starts_header {
+                    self.header_depth = Some(0);
+                }
+            }
+            ("(", Some(depth)) => self.header_depth = Some(depth + 1),
+            (")", Some(0)) => self.header_depth = None,
+            (")", Some(depth)) => self.header_depth = Some(depth - 1),
+            _ => {}
+        }
+    }
+}
+
+fn is_identifier_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_'
+}
+
+fn identifier_length(text: &str) -> usize {
+    text.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .unwrap_or(text.len())
+}
+
+/// The length of the string literal at the start of `text`, up to the end of
+/// the input when it is never closed.
+fn string_length(text: &str) -> usize {
+    let mut escaped = false;
+    for (index, c) in text.char_indices().skip(1) {
+        match c {
+            '\\' if !escaped => escaped = true,
+            '"' if !escaped => return index + 1,
+            _ => escaped = false,
+        }
+    }
+    text.len()
+}
+
+fn number_length(text: &str) -> usize {
+    let digits = |from: usize| {
+        text[from..]
+            .find(|c: char| !c.is_ascii_digit())
+            .map_or(text.len(), |length| from + length)
+    };
+    let mut length = digits(0);
+    if text[length..].starts_with('.')
+        && text[length + 1..].starts_with(|c: char| c.is_ascii_digit())
+    {
+        length = digits(length + 1);
+    }
+    length
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use TokenKind::*;
+
+    /// The tokens of `source` other than whitespace, after checking that
+    /// together they still spell out all of it.
+    fn tokens(source: &str) -> Vec<(TokenKind, &str)> {
+        let tokens = tokenize(source);
+        let rebuilt: String = tokens.iter().map(|token| token.text).collect();
+        assert_eq!(rebuilt, source);
+        tokens
+            .into_iter()
+            .filter(|token| token.kind != Whitespace)
+            .map(|token| (token.kind, token.text))
+            .collect()
+    }
+
+    #[test]
+    fn header_parameters_are_told_apart_from_properties() {
+        let source = "QUERY getCountries(max_population: I64) =>\n    \
+            countries <- N<Country>::WHERE(_::{population}::LT(max_population))\n    \
+            RETURN countries";
+
+        assert_eq!(
+            tokens(source),
+            [
+                (Keyword, "QUERY"),
+                (Identifier, "getCountries"),
+                (Punctuation, "("),
+                (Parameter, "max_population"),
+                (Punctuation, ":"),
+                (TypeName, "I64"),
+                (Punctuation, ")"),
+                (Punctuation, "=>"),
+                (Identifier, "countries"),
+                (Punctuation, "<-"),
+                (Keyword, "N"),
+                (Punctuation, "<"),
+                (TypeName, "Country"),
+                (Punctuation, ">"),
+                (Step, "::WHERE"),
+                (Punctuation, "("),
+                (Keyword, "_"),
+                (Punctuation, "::"),
+                (Punctuation, "{"),
+                (Identifier, "population"),
+                (Punctuation, "}"),
+                (Step, "::LT"),
+                (Punctuation, "("),
+                (Parameter, "max_population"),
+                (Punctuation, ")"),
+                (Punctuation, ")"),
+                (Keyword, "RETURN"),
+                (Identifier, "countries"),
+            ]
+        );
+    }
+
+    #[test]
+    fn schema_types_are_not_traversal_steps() {
+        assert_eq!(
+            tokens("N::Country {\n    name: String\n}"),
+            [
+                (Keyword, "N::"),
+                (TypeName, "Country"),
+                (Punctuation, "{"),
+                (Identifier, "name"),
+                (Punctuation, ":"),
+                (TypeName, "String"),
+                (Punctuation, "}"),
+            ]
+        );
+        assert_eq!(
+            tokens("E::Country_to_City {\n    From: Country,\n    To: City,\n}")[..7],
+            [
+                (Keyword, "E::"),
+                (TypeName, "Country_to_City"),
+                (Punctuation, "{"),
+                (Keyword, "From"),
+                (Punctuation, ":"),
+                (TypeName, "Country"),
+                (Punctuation, ","),
+            ]
+        );
+        assert_eq!(
+            tokens("country::Out<Country_to_City>")[..3],
+            [(Identifier, "country"), (Step, "::Out"), (Punctuation, "<"),]
+        );
+    }
+
+    #[test]
+    fn unterminated_strings_run_to_the_end() {
+        assert_eq!(
+            tokens("RETURN \"Berl\\\"in"),
+            [(Keyword, "RETURN"), (Literal, "\"Berl\\\"in")]
+        );
+        assert_eq!(
+            tokens("\"Köln\" 42"),
+            [(Literal, "\"Köln\""), (Literal, "42")]
+        );
+    }
+
+    #[test]
+    fn prose_is_not_hql() {
+        assert!(!contains_hql(
+            "To find a country, write a query for it in the file and run it."
+        ));
+        assert!(!contains_hql("From here on, Out and In are just words."));
+        assert!(contains_hql("Use N<Country> to get every country."));
+        assert!(contains_hql("Follow the edge with ::Out<Country_to_City>."));
+    }
+}
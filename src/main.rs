@@ -1,15 +1,69 @@
 mod app;
+mod config;
 mod formatter;
+mod helix_cli;
 mod lesson_types;
 mod lessons;
+mod lexer;
+mod project;
+#[cfg(test)]
+mod test_support;
+mod theme;
 mod ui;
 mod validation;
 
 use app::App;
+use config::{Args, Config, Invocation};
+use helix_cli::ProcessHelixCli;
 use helixir_macros::parse_answers;
+use project::Project;
+use std::io::IsTerminal;
+use std::path::PathBuf;
 
 #[parse_answers]
 fn main() {
-    let mut app = App::new(lessons);
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(Invocation::Run(args)) => args,
+        Ok(Invocation::Help) => {
+            println!("{}", config::USAGE);
+            return;
+        }
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, config::USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    // redirected output gets plain text rather than colour codes
+    if !std::io::stdout().is_terminal() {
+        colored::control::set_override(false);
+    }
+
+    let root = match args.project() {
+        Some(dir) => std::path::absolute(dir).unwrap_or_else(|_| dir.to_path_buf()),
+        None => {
+            let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+            Project::discover(&cwd)
+        }
+    };
+    if !root.is_dir() {
+        exit_with_error(&format!(
+            "Project directory '{}' does not exist",
+            root.display()
+        ));
+    }
+
+    let project = Project::load(&root).unwrap_or_else(|e| exit_with_error(&e));
+    let config = Config::load(args, &project).unwrap_or_else(|e| exit_with_error(&e));
+    let helix = ProcessHelixCli::new(&config.helix_binary, &config.instance, &project.root);
+    project::init(project);
+    config::init(config);
+
+    let mut app = App::new(lessons, Box::new(helix));
     app.run();
 }
+
+fn exit_with_error(message: &str) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(2);
+}
@@ -0,0 +1,140 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use serde::Deserialize;
+
+const MANIFEST_FILE: &str = "helix.toml";
+const DEFAULT_QUERIES_DIR: &str = "db";
+const PROGRESS_FILE: &str = "instance.json";
+
+static PROJECT: OnceLock<Project> = OnceLock::new();
+
+/// The Helix project the learner is working in, as described by its helix.toml.
+/// Projects without a helix.toml use the `db/` layout created by `helix init`.
+#[derive(Debug, Clone)]
+pub struct Project {
+    pub root: PathBuf,
+    pub queries_dir: PathBuf,
+    /// Local instances from the `[local.<name>]` tables, keyed by name.
+    pub instances: BTreeMap<String, LocalInstance>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct LocalInstance {
+    pub port: Option<u16>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Manifest {
+    #[serde(default)]
+    project: ProjectSection,
+    #[serde(default)]
+    local: BTreeMap<String, LocalInstance>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ProjectSection {
+    queries: Option<PathBuf>,
+}
+
+impl Project {
+    pub const DEFAULT_INSTANCE: &str = "dev";
+
+    /// Reads `helix.toml` in `root`, falling back to the default layout when
+    /// there is none.
+    pub fn load(root: &Path) -> Result<Self, String> {
+        let path = root.join(MANIFEST_FILE);
+        let manifest: Manifest = match std::fs::read_to_string(&path) {
+            Ok(content) => toml::from_str(&content)
+                .map_err(|e| format!("Invalid {}: {}", path.display(), e))?,
+            Err(_) => Manifest::default(),
+        };
+
+        let queries_dir = manifest
+            .project
+            .queries
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_QUERIES_DIR));
+        // helix init writes `queries = "./db/"`
+        let queries_dir = queries_dir
+            .strip_prefix(".")
+            .map(Path::to_path_buf)
+            .unwrap_or(queries_dir);
+
+        Ok(Self {
+            root: root.to_path_buf(),
+            queries_dir: root.join(queries_dir),
+            instances: manifest.local,
+        })
+    }
+
+    /// Walks up from `start` to the first directory that looks like a Helix
+    /// project: one with a helix.toml, a `db/` directory or helixir progress.
+    /// Falls back to `start` so a new project is created where helixir was run.
+    pub fn discover(start: &Path) -> PathBuf {
+        start
+            .ancestors()
+            .find(|dir| {
+                dir.join(MANIFEST_FILE).is_file()
+                    || dir.join(DEFAULT_QUERIES_DIR).is_dir()
+                    || dir.join(PROGRESS_FILE).is_file()
+            })
+            .unwrap_or(start)
+            .to_path_buf()
+    }
+
+    /// helixir's own progress and deployment state.
+    pub fn progress_file(&self) -> PathBuf {
+        self.root.join(PROGRESS_FILE)
+    }
+
+    /// The instance to deploy when none is configured: `dev` if the project
+    /// defines it (or defines no instances at all), otherwise the first one.
+    pub fn default_instance(&self) -> String {
+        if self.instances.is_empty() || self.instances.contains_key(Self::DEFAULT_INSTANCE) {
+            return Self::DEFAULT_INSTANCE.to_string();
+        }
+        self.instances
+            .keys()
+            .next()
+            .cloned()
+            .unwrap_or_else(|| Self::DEFAULT_INSTANCE.to_string())
+    }
+
+    pub fn port(&self, instance: &str) -> Option<u16> {
+        self.instances.get(instance).and_then(|local| local.port)
+    }
+
+    /// Errors when helix.toml defines local instances but not `instance`,
+    /// since `helix build` would fail on it anyway.
+    pub fn check_instance(&self, instance: &str) -> Result<(), String> {
+        if self.instances.is_empty() || self.instances.contains_key(instance) {
+            return Ok(());
+        }
+
+        let defined: Vec<&str> = self.instances.keys().map(String::as_str).collect();
+        Err(format!(
+            "Instance '{}' is not defined in {} (defined: {})",
+            instance,
+            MANIFEST_FILE,
+            defined.join(", ")
+        ))
+    }
+}
+
+/// Makes `project` the project returned by [`project`]. Only the first call
+/// has any effect.
+pub fn init(project: Project) {
+    let _ = PROJECT.set(project);
+}
+
+pub fn project() -> &'static Project {
+    PROJECT.get_or_init(|| {
+        let root = PathBuf::from(".");
+        Project::load(&root).unwrap_or_else(|_| Project {
+            queries_dir: root.join(DEFAULT_QUERIES_DIR),
+            root,
+            instances: BTreeMap::new(),
+        })
+    })
+}
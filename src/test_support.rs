@@ -0,0 +1,68 @@
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::sync::LazyLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use std::{fs, thread};
+
+use crate::config::{self, Config};
+use crate::project::{self, Project};
+
+/// Makes the stand-in instance accept connections without answering them,
+/// as a pushed instance does while it is still starting.
+pub static INSTANCE_STARTING: AtomicBool = AtomicBool::new(false);
+
+/// A scratch project with a schema and a query, and a local server that
+/// answers every query with `{}` in place of the pushed instance.
+static PROJECT_ROOT: LazyLock<PathBuf> = LazyLock::new(|| {
+    let root = std::env::temp_dir().join(format!("helixir-test-{}", std::process::id()));
+    let queries_dir = root.join("db");
+    fs::create_dir_all(&queries_dir).unwrap();
+    fs::write(
+        queries_dir.join("schema.hx"),
+        "N::Continent {\n    name: String\n}\n",
+    )
+    .unwrap();
+    fs::write(
+        queries_dir.join("queries.hx"),
+        "QUERY getAllContinents() =>\n    continents <- N<Continent>\n    RETURN continents\n",
+    )
+    .unwrap();
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            if INSTANCE_STARTING.load(Ordering::SeqCst) {
+                thread::spawn(move || {
+                    thread::sleep(Duration::from_secs(5));
+                    drop(stream);
+                });
+                continue;
+            }
+            let _ = stream.read(&mut [0; 4096]);
+            let _ = stream.write_all(
+                b"HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: 2\r\nconnection: close\r\n\r\n{}",
+            );
+        }
+    });
+
+    config::init(Config {
+        endpoint: format!("http://{}", address),
+        ..Config::default()
+    });
+    project::init(Project {
+        root: root.clone(),
+        queries_dir,
+        instances: Default::default(),
+    });
+    root
+});
+
+/// Sets up the scratch project and the stand-in instance. `config` and
+/// `project` are set once per process, so every test that reads either one
+/// calls this first, whichever test happens to run first.
+pub fn init() {
+    LazyLock::force(&PROJECT_ROOT);
+}
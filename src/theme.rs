@@ -0,0 +1,95 @@
+use colored::{Color, ColoredString, Colorize};
+use serde::Deserialize;
+
+use crate::config::config;
+
+/// The colour scheme everything is drawn in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Theme {
+    #[default]
+    Dark,
+    Light,
+    HighContrast,
+    /// No colour at all, as asked for by `NO_COLOR`. Bold and underline are
+    /// kept, as they do not depend on the terminal's colours.
+    Monochrome,
+}
+
+/// The slots of the palette, named after their hue in the dark theme. Each
+/// theme fills them with colours that read well on its own background.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tone {
+    Text,
+    Subtext,
+    /// Secondary details such as unchanged diff lines and comments.
+    Overlay,
+    Red,
+    Green,
+    Yellow,
+    Sky,
+    Blue,
+    Mauve,
+    Teal,
+    Peach,
+}
+
+impl Theme {
+    fn color(self, tone: Tone) -> Option<Color> {
+        let rgb = |r, g, b| Some(Color::TrueColor { r, g, b });
+        match self {
+            Theme::Dark => match tone {
+                Tone::Text => rgb(202, 211, 245),
+                Tone::Subtext => rgb(184, 192, 224),
+                Tone::Overlay => rgb(128, 135, 162),
+                Tone::Red => rgb(237, 135, 150),
+                Tone::Green => rgb(166, 218, 149),
+                Tone::Yellow => rgb(238, 212, 159),
+                Tone::Sky => rgb(145, 215, 227),
+                Tone::Blue => rgb(138, 173, 244),
+                Tone::Mauve => rgb(198, 160, 246),
+                Tone::Teal => rgb(139, 213, 202),
+                Tone::Peach => rgb(245, 169, 127),
+            },
+            Theme::Light => match tone {
+                Tone::Text => rgb(76, 79, 105),
+                Tone::Subtext => rgb(92, 95, 119),
+                Tone::Overlay => rgb(124, 127, 147),
+                Tone::Red => rgb(210, 15, 57),
+                Tone::Green => rgb(64, 160, 43),
+                Tone::Yellow => rgb(223, 142, 29),
+                Tone::Sky => rgb(4, 165, 229),
+                Tone::Blue => rgb(30, 102, 245),
+                Tone::Mauve => rgb(136, 57, 239),
+                Tone::Teal => rgb(23, 146, 153),
+                Tone::Peach => rgb(254, 100, 11),
+            },
+            // the terminal's own bright colours, so they follow its palette
+            Theme::HighContrast => Some(match tone {
+                Tone::Text => Color::BrightWhite,
+                Tone::Subtext | Tone::Overlay => Color::White,
+                Tone::Red => Color::BrightRed,
+                Tone::Green => Color::BrightGreen,
+                Tone::Yellow | Tone::Peach => Color::BrightYellow,
+                Tone::Sky | Tone::Teal => Color::BrightCyan,
+                Tone::Blue => Color::BrightBlue,
+                Tone::Mauve => Color::BrightMagenta,
+            }),
+            Theme::Monochrome => None,
+        }
+    }
+}
+
+/// Colours text with a tone of the configured theme.
+pub trait Themed {
+    fn themed(&self, tone: Tone) -> ColoredString;
+}
+
+impl<T: AsRef<str> + ?Sized> Themed for T {
+    fn themed(&self, tone: Tone) -> ColoredString {
+        match config().theme.color(tone) {
+            Some(color) => self.as_ref().color(color),
+            None => self.as_ref().normal(),
+        }
+    }
+}
@@ -1,11 +1,67 @@
+use crate::config::config;
 use crate::formatter::HelixFormatter;
 use crate::lessons::get_lesson;
-use std::io;
+use crate::theme::{Themed, Tone};
+use colored::*;
+use std::collections::VecDeque;
+use std::io::{self, IsTerminal, Write};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::{LazyLock, Mutex, Once};
+use std::time::{Duration, Instant};
+
+/// What the tutorial wakes up for while it waits on the next command.
+enum InputEvent {
+    Line(io::Result<String>),
+    /// The terminal was resized, so the lesson should be laid out again.
+    Resize,
+}
+
+/// A rendezvous channel, so a resize only gets through while the tutorial
+/// is blocked on the next command, the only time a redraw does not cut into
+/// other output.
+static INPUT_EVENTS: LazyLock<(SyncSender<InputEvent>, Mutex<Receiver<InputEvent>>)> =
+    LazyLock::new(|| {
+        let (sender, receiver) = mpsc::sync_channel(0);
+        (sender, Mutex::new(receiver))
+    });
+
+/// Reads stdin on its own thread, so waiting for a line does not keep the
+/// input loop from handling resizes.
+static STDIN_READER: Once = Once::new();
 
 pub fn clear_screen() {
+    // redirected output keeps its history rather than escape codes, and
+    // screen readers lose their place when the screen is wiped
+    if !io::stdout().is_terminal() || config().accessible {
+        return;
+    }
     clearscreen::clear().expect("Failed to clear screen");
 }
 
+/// Lays the current lesson out again at the new width whenever the terminal
+/// is resized. Only Unix terminals report resizes; elsewhere the next screen
+/// picks up the new size.
+pub async fn watch_resize() {
+    // nothing is laid out to the width in accessibility mode
+    if config().accessible {
+        return;
+    }
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        let Ok(mut resizes) = signal(SignalKind::window_change()) else {
+            return;
+        };
+        while resizes.recv().await.is_some() {
+            // dragging a window edge sends a burst of signals; draw once it settles
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            // the input loop draws the screen, and only when it is idle
+            let _ = INPUT_EVENTS.0.try_send(InputEvent::Resize);
+        }
+    }
+}
+
 #[allow(dead_code)]
 pub fn welcome_screen() {
     let current_lesson = 0;
@@ -23,10 +79,120 @@ pub fn display_lesson(lesson_id: usize) {
     formatter.display_lesson(&lesson.title, lesson.id, &lesson.instructions);
 }
 
+/// Waits for the next command, laying the lesson out again whenever the
+/// terminal is resized in the meantime.
 pub fn get_user_input() -> String {
-    let mut input = String::new();
-    io::stdin()
-        .read_line(&mut input)
-        .expect("failed to read input");
-    input
+    STDIN_READER.call_once(|| {
+        let lines = INPUT_EVENTS.0.clone();
+        std::thread::spawn(move || {
+            loop {
+                let mut input = String::new();
+                let read = io::stdin().read_line(&mut input).map(|_| input);
+                if lines.send(InputEvent::Line(read)).is_err() {
+                    break;
+                }
+            }
+        });
+    });
+
+    let events = INPUT_EVENTS.1.lock().expect("input events lock poisoned");
+    loop {
+        match events.recv().expect("input channel closed") {
+            InputEvent::Line(read) => return read.expect("failed to read input"),
+            InputEvent::Resize => {
+                clear_screen();
+                HelixFormatter::new().redraw();
+            }
+        }
+    }
+}
+
+const SPINNER_FRAMES: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+const SPINNER_TAIL_LINES: usize = 4;
+
+/// A progress line with the last few lines of command output underneath,
+/// redrawn in place while a long-running helix command is in flight.
+pub struct Spinner {
+    label: String,
+    started: Instant,
+    frame: usize,
+    tail: VecDeque<String>,
+    drawn_lines: usize,
+    /// Whether the label has been printed once in place of the animation.
+    announced: bool,
+}
+
+impl Spinner {
+    pub fn new(label: impl Into<String>) -> Self {
+        Self {
+            label: label.into(),
+            started: Instant::now(),
+            frame: 0,
+            tail: VecDeque::with_capacity(SPINNER_TAIL_LINES),
+            drawn_lines: 0,
+            announced: false,
+        }
+    }
+
+    pub fn push_line(&mut self, line: &str) {
+        let line = line.trim();
+        if line.is_empty() {
+            return;
+        }
+        if self.tail.len() == SPINNER_TAIL_LINES {
+            self.tail.pop_front();
+        }
+        // lines that wrap would throw off how far `erase` moves back up
+        let width = terminal_size::terminal_size()
+            .map_or(80, |(terminal_size::Width(width), _)| width as usize);
+        self.tail
+            .push_back(line.chars().take(width.saturating_sub(4)).collect());
+    }
+
+    pub fn tick(&mut self) {
+        self.frame = (self.frame + 1) % SPINNER_FRAMES.len();
+        self.draw();
+    }
+
+    pub fn finish(&mut self) {
+        self.erase();
+        let _ = io::stdout().flush();
+    }
+
+    fn draw(&mut self) {
+        // redrawing in place needs a terminal, and screen readers would
+        // announce every frame, so they are told once what is running
+        if !io::stdout().is_terminal() || config().accessible {
+            if !self.announced {
+                println!("{}...", self.label);
+                self.announced = true;
+            }
+            return;
+        }
+        self.erase();
+
+        let mut stdout = io::stdout();
+        let _ = writeln!(
+            stdout,
+            "{} {} {}",
+            SPINNER_FRAMES[self.frame].themed(Tone::Mauve).bold(),
+            self.label.themed(Tone::Text),
+            format!("({}s, Ctrl-C to cancel)", self.started.elapsed().as_secs())
+                .themed(Tone::Subtext)
+        );
+        for line in &self.tail {
+            let _ = writeln!(stdout, "  {}", line.themed(Tone::Overlay));
+        }
+        let _ = stdout.flush();
+
+        self.drawn_lines = 1 + self.tail.len();
+    }
+
+    fn erase(&mut self) {
+        if self.drawn_lines > 0 {
+            // move the cursor back over the previous frame and clear everything below it
+            print!("\x1b[{}A\x1b[J", self.drawn_lines);
+            self.drawn_lines = 0;
+        }
+    }
 }
@@ -0,0 +1,127 @@
+use serde_json::Value;
+
+use crate::validation::executor::result_mismatch;
+use crate::validation::{
+    QueryCase, QueryValidator, expand_embeddings, item_matches, result_sets_match,
+};
+
+impl QueryValidator {
+    /// Runs `query_name` with the input of `case` and checks the returned
+    /// items against what the case expects.
+    pub async fn run_case(
+        &self,
+        query_name: &str,
+        case: &QueryCase,
+    ) -> anyhow::Result<(bool, String)> {
+        let input = self.replace_placeholder_ids(expand_embeddings(case.input.clone()))?;
+        let response: serde_json::Value = self.execute_query(query_name, &input).await?;
+
+        let items = result_items(&response);
+        let names: Vec<&str> = items.iter().filter_map(|item| item_name(item)).collect();
+        let expect = &case.expect;
+
+        let mut failures = Vec::new();
+        if expect.empty && !items.is_empty() {
+            failures.push(format!("expected no results, got {}", items.len()));
+        }
+        if let Some(count) = expect.count
+            && count != items.len()
+        {
+            failures.push(format!("expected {} results, got {}", count, items.len()));
+        }
+        for name in &expect.contains {
+            if !names.contains(&name.as_str()) {
+                failures.push(format!("expected '{}' in the results", name));
+            }
+        }
+        for name in &expect.excludes {
+            if names.contains(&name.as_str()) {
+                failures.push(format!("'{}' should not be in the results", name));
+            }
+        }
+        if let Some(expected) = &expect.results
+            && let Err(failure) = result_sets_match(&items, expected)
+        {
+            failures.push(failure);
+        }
+
+        if failures.is_empty() {
+            return Ok((
+                true,
+                format!(
+                    "{} ({} result{})",
+                    case.description,
+                    items.len(),
+                    if items.len() == 1 { "" } else { "s" }
+                ),
+            ));
+        }
+
+        let summary = format!(
+            "{}: {}\nInput: {}",
+            case.description,
+            failures.join("; "),
+            input
+        );
+        let expected = match &expect.results {
+            Some(expected) => Some(expected.clone()),
+            None if expect.empty => Some(Vec::new()),
+            None => None,
+        };
+        let message = match expected {
+            Some(expected) => {
+                let items = aligned_items(&items, &expected);
+                result_mismatch(summary, Value::Array(expected), &items)
+            }
+            None => format!(
+                "{}\nDatabase result:\n{}",
+                summary,
+                serde_json::to_string_pretty(&response).unwrap_or_default()
+            ),
+        };
+        Ok((false, message))
+    }
+}
+
+/// The returned items reordered so each one sits at the position of the
+/// expected item it matches, or shares a name with, for a positional diff.
+/// Items that correspond to nothing expected come last.
+fn aligned_items(items: &[&Value], expected: &[Value]) -> Vec<Value> {
+    let mut remaining: Vec<&Value> = items.to_vec();
+    let mut aligned = Vec::new();
+    for expected in expected {
+        let position = remaining
+            .iter()
+            .position(|item| item_matches(item, expected))
+            .or_else(|| {
+                let name = item_name(expected)?;
+                remaining
+                    .iter()
+                    .position(|item| item_name(item) == Some(name))
+            });
+        match position {
+            Some(position) => aligned.push(remaining.remove(position).clone()),
+            None => break,
+        }
+    }
+    aligned.extend(remaining.into_iter().cloned());
+    aligned
+}
+
+/// The items a query returned: the elements of the first array in the
+/// response, or the single object it returned.
+fn result_items(response: &serde_json::Value) -> Vec<&serde_json::Value> {
+    let Some(fields) = response.as_object() else {
+        return Vec::new();
+    };
+
+    if let Some(items) = fields.values().find_map(|value| value.as_array()) {
+        return items.iter().collect();
+    }
+    fields.values().filter(|value| value.is_object()).collect()
+}
+
+/// Property projections such as `::{name}` return each value in an array.
+fn item_name(item: &serde_json::Value) -> Option<&str> {
+    item["name"].as_str().or_else(|| item["name"][0].as_str())
+}
@@ -0,0 +1,198 @@
+use std::collections::{BTreeMap, HashSet};
+
+use serde_json::json;
+
+use crate::validation::executor::EntityType;
+use crate::validation::{
+    DuplicateNodes, ParsedSchema, QueryValidator, StaleEntity, all_edges_query, all_nodes_query,
+    created_entity_ids, drop_edge_query, drop_node_query, drop_vectors_query, load_instance_data,
+    prune_created_entities,
+};
+
+impl QueryValidator {
+    /// Every node of type `label`, via the deployed helper queries.
+    pub async fn list_nodes(&self, label: &str) -> anyhow::Result<Vec<serde_json::Value>> {
+        let response: serde_json::Value = self
+            .query_with_retry(&all_nodes_query(label), &json!({}))
+            .await?;
+
+        Ok(first_array(response))
+    }
+
+    /// Every edge of type `label`, via the deployed helper queries.
+    pub async fn list_edges(&self, label: &str) -> anyhow::Result<Vec<serde_json::Value>> {
+        let response: serde_json::Value = self
+            .query_with_retry(&all_edges_query(label), &json!({}))
+            .await?;
+
+        Ok(first_array(response))
+    }
+
+    pub async fn node_exists(&self, label: &str, id: &str) -> anyhow::Result<bool> {
+        Ok(self.node_by_id(label, id).await?.is_some())
+    }
+
+    pub async fn drop_node(&self, label: &str, id: &str) -> anyhow::Result<()> {
+        self.query_with_retry::<_, serde_json::Value>(
+            &drop_node_query(label),
+            &json!({ "id": id }),
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn drop_edge(&self, label: &str, id: &str) -> anyhow::Result<()> {
+        self.query_with_retry::<_, serde_json::Value>(
+            &drop_edge_query(label),
+            &json!({ "id": id }),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Groups nodes that share a type and a `name`, for every node type in
+    /// `schema` that has a name property.
+    pub async fn find_duplicate_nodes(
+        &self,
+        schema: &ParsedSchema,
+    ) -> anyhow::Result<Vec<DuplicateNodes>> {
+        let tracked = created_entity_ids();
+        let mut labels: Vec<&String> = schema
+            .nodes
+            .iter()
+            .filter(|(_, properties)| properties.iter().any(|p| p.name == "name"))
+            .map(|(label, _)| label)
+            .collect();
+        labels.sort();
+
+        let mut duplicates = Vec::new();
+        for label in labels {
+            let mut by_name: BTreeMap<String, Vec<String>> = BTreeMap::new();
+            for node in self.list_nodes(label).await? {
+                if let (Some(name), Some(id)) = (node["name"].as_str(), node["id"].as_str()) {
+                    by_name
+                        .entry(name.to_string())
+                        .or_default()
+                        .push(id.to_string());
+                }
+            }
+
+            for (name, mut ids) in by_name {
+                if ids.len() < 2 {
+                    continue;
+                }
+                let keep_index = ids.iter().position(|id| tracked.contains(id)).unwrap_or(0);
+                ids.remove(keep_index);
+                duplicates.push(DuplicateNodes {
+                    label: label.clone(),
+                    name,
+                    extra: ids,
+                });
+            }
+        }
+
+        Ok(duplicates)
+    }
+
+    /// Drops the extra copies and forgets them in instance.json. Returns how
+    /// many nodes were removed.
+    pub async fn remove_duplicate_nodes(
+        &self,
+        duplicates: &[DuplicateNodes],
+    ) -> anyhow::Result<usize> {
+        let mut removed = HashSet::new();
+        let mut result = Ok(());
+        'groups: for group in duplicates {
+            for id in &group.extra {
+                if let Err(e) = self.drop_node(&group.label, id).await {
+                    result = Err(e);
+                    break 'groups;
+                }
+                removed.insert(id.clone());
+            }
+        }
+
+        // forget whatever was removed even if a later drop failed
+        prune_created_entities(&removed).map_err(|e| anyhow::anyhow!(e))?;
+        result.map(|_| removed.len())
+    }
+
+    /// Drops every vector and node of the types in `schema`, and with them
+    /// every edge. Returns how many nodes were removed.
+    pub async fn wipe(&self, schema: &ParsedSchema) -> anyhow::Result<usize> {
+        let mut vector_edges: Vec<&String> = schema
+            .edges
+            .iter()
+            .filter(|(_, edge)| schema.vectors.contains_key(&edge.to_type))
+            .map(|(label, _)| label)
+            .collect();
+        vector_edges.sort();
+        for label in vector_edges {
+            self.query_with_retry::<_, serde_json::Value>(&drop_vectors_query(label), &json!({}))
+                .await?;
+        }
+
+        let mut labels: Vec<&String> = schema.nodes.keys().collect();
+        labels.sort();
+        let mut removed = 0;
+        for label in labels {
+            for node in self.list_nodes(label).await? {
+                if let Some(id) = node["id"].as_str() {
+                    self.drop_node(label, id).await?;
+                    removed += 1;
+                }
+            }
+        }
+
+        Ok(removed)
+    }
+
+    /// Entities recorded in instance.json that the instance no longer has,
+    /// e.g. after it was pushed against a fresh store. Each recorded id is
+    /// looked up with the node-by-id helper query.
+    pub async fn stale_created_entities(&self) -> anyhow::Result<Vec<StaleEntity>> {
+        let instance_data = load_instance_data();
+        let Some(created) = instance_data["created_entities"].as_object() else {
+            return Ok(Vec::new());
+        };
+
+        let mut stale = Vec::new();
+        for (key, entities) in created {
+            let Some(label) = EntityType::from_storage_key(key).map(|t| t.label()) else {
+                continue;
+            };
+            let Some(entities) = entities.as_array().filter(|e| !e.is_empty()) else {
+                continue;
+            };
+
+            for entity in entities {
+                let Some(id) = entity["id"].as_str() else {
+                    continue;
+                };
+                if self.node_by_id(label, id).await?.is_none() {
+                    stale.push(StaleEntity {
+                        label: label.to_string(),
+                        id: id.to_string(),
+                        name: entity["name"].as_str().map(str::to_string),
+                    });
+                }
+            }
+        }
+
+        Ok(stale)
+    }
+}
+
+/// The helper queries return a single named array.
+fn first_array(response: serde_json::Value) -> Vec<serde_json::Value> {
+    match response {
+        serde_json::Value::Object(fields) => fields
+            .into_iter()
+            .find_map(|(_, value)| match value {
+                serde_json::Value::Array(items) => Some(items),
+                _ => None,
+            })
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    }
+}
@@ -0,0 +1,224 @@
+use serde_json::Value;
+
+use crate::config::config;
+
+/// Whether two F64 values are the same within the configured relative or
+/// absolute tolerance.
+pub fn floats_match(actual: f64, expected: f64) -> bool {
+    if actual == expected {
+        return true;
+    }
+    let difference = (actual - expected).abs();
+    difference <= config().absolute_tolerance
+        || difference <= config().relative_tolerance * actual.abs().max(expected.abs())
+}
+
+/// Whether two vectors have the same length and matching components.
+pub fn vectors_match(actual: &[f64], expected: &[f64]) -> bool {
+    actual.len() == expected.len()
+        && actual
+            .iter()
+            .zip(expected)
+            .all(|(actual, expected)| floats_match(*actual, *expected))
+}
+
+/// Compares JSON values with numbers canonicalised, so `4259`, `4259.0` and
+/// `4.259e3` are equal and floats only need to match within tolerance.
+/// Arrays are compared in order.
+pub fn values_match(actual: &Value, expected: &Value) -> bool {
+    match (actual, expected) {
+        (Value::Number(actual), Value::Number(expected)) => {
+            match (actual.as_i64(), expected.as_i64()) {
+                // large integers lose precision as f64
+                (Some(actual), Some(expected)) => actual == expected,
+                _ => match (actual.as_f64(), expected.as_f64()) {
+                    (Some(actual), Some(expected)) => floats_match(actual, expected),
+                    _ => actual == expected,
+                },
+            }
+        }
+        (Value::Array(actual), Value::Array(expected)) => {
+            actual.len() == expected.len()
+                && actual
+                    .iter()
+                    .zip(expected)
+                    .all(|(actual, expected)| values_match(actual, expected))
+        }
+        (Value::Object(actual), Value::Object(expected)) => {
+            actual.len() == expected.len()
+                && expected
+                    .iter()
+                    .all(|(key, value)| actual.get(key).is_some_and(|a| values_match(a, value)))
+        }
+        _ => actual == expected,
+    }
+}
+
+/// Whether `item` has every property of `expected` with a matching value.
+/// Properties `expected` leaves out, such as generated ids, are ignored.
+pub fn item_matches(item: &Value, expected: &Value) -> bool {
+    match expected.as_object() {
+        Some(properties) => properties
+            .iter()
+            .all(|(key, value)| values_match(&item[key.as_str()], value)),
+        None => values_match(item, expected),
+    }
+}
+
+/// Compares a result set with the expected items in any order, for queries
+/// whose lesson does not ask for a particular ordering. Each expected item
+/// must match a different returned item, and nothing else may be returned.
+pub fn result_sets_match(items: &[&Value], expected: &[Value]) -> Result<(), String> {
+    if let Some(pairing) = pair_items(items, expected, &mut vec![false; items.len()]) {
+        if items.len() == expected.len() {
+            return Ok(());
+        }
+        let unexpected: Vec<String> = (0..items.len())
+            .filter(|index| !pairing.contains(index))
+            .map(|index| items[index].to_string())
+            .collect();
+        return Err(format!("unexpected results {}", unexpected.join(", ")));
+    }
+
+    let missing: Vec<String> = expected
+        .iter()
+        .filter(|expected| !items.iter().any(|item| item_matches(item, expected)))
+        .map(Value::to_string)
+        .collect();
+    if missing.is_empty() {
+        Err("the results do not match the expected ones one to one".to_string())
+    } else {
+        Err(format!("no result matches {}", missing.join(", ")))
+    }
+}
+
+/// Pairs each expected item with a distinct returned item, backtracking when
+/// an earlier choice leaves a later item without a partner. Returns the
+/// index of the item chosen for each expected one.
+fn pair_items(items: &[&Value], expected: &[Value], used: &mut [bool]) -> Option<Vec<usize>> {
+    let Some((first, rest)) = expected.split_first() else {
+        return Some(Vec::new());
+    };
+
+    for index in 0..items.len() {
+        if used[index] || !item_matches(items[index], first) {
+            continue;
+        }
+        used[index] = true;
+        if let Some(mut pairing) = pair_items(items, rest, used) {
+            pairing.push(index);
+            return Some(pairing);
+        }
+        used[index] = false;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::test_support;
+
+    #[test]
+    fn floats_match_within_the_relative_tolerance() {
+        test_support::init();
+        let relative = config().relative_tolerance;
+
+        assert!(floats_match(1000.0, 1000.0 * (1.0 + relative / 2.0)));
+        assert!(!floats_match(1000.0, 1000.0 * (1.0 + relative * 2.0)));
+    }
+
+    #[test]
+    fn floats_near_zero_match_within_the_absolute_tolerance() {
+        test_support::init();
+        let absolute = config().absolute_tolerance;
+
+        // next to zero the relative tolerance allows next to nothing
+        assert!(floats_match(0.0, absolute / 2.0));
+        assert!(floats_match(-absolute / 2.0, absolute / 2.0));
+        assert!(!floats_match(0.0, absolute * 2.0));
+    }
+
+    #[test]
+    fn vectors_of_different_lengths_do_not_match() {
+        test_support::init();
+        assert!(vectors_match(&[0.1, 0.2], &[0.1, 0.2]));
+        assert!(!vectors_match(&[0.1, 0.2], &[0.1, 0.2, 0.0]));
+        assert!(!vectors_match(&[], &[0.1]));
+    }
+
+    #[test]
+    fn integer_and_float_numbers_are_equal() {
+        test_support::init();
+        assert!(values_match(&json!(4259), &json!(4259.0)));
+        assert!(values_match(&json!(4259.0), &json!(4.259e3)));
+        assert!(values_match(&json!({"gdp": 3131}), &json!({"gdp": 3131.0})));
+        assert!(!values_match(&json!(4259), &json!(4260.0)));
+        // beyond f64 precision only an exact integer match counts
+        assert!(!values_match(
+            &json!(9_007_199_254_740_993_i64),
+            &json!(9_007_199_254_740_992_i64)
+        ));
+    }
+
+    #[test]
+    fn result_sets_match_in_any_order() {
+        test_support::init();
+        let items = [json!({"name": "Germany"}), json!({"name": "France"})];
+        let items: Vec<&Value> = items.iter().collect();
+
+        assert!(
+            result_sets_match(
+                &items,
+                &[json!({"name": "France"}), json!({"name": "Germany"})]
+            )
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn result_sets_backtrack_when_a_duplicate_row_is_paired_too_early() {
+        test_support::init();
+        let items = [
+            json!({"name": "Germany", "gdp": 4259.0}),
+            json!({"name": "Germany", "gdp": 3131.0}),
+        ];
+        let items: Vec<&Value> = items.iter().collect();
+
+        // pairing the first expected row with the first item leaves the
+        // second expected row without a match
+        let expected = [
+            json!({"name": "Germany"}),
+            json!({"name": "Germany", "gdp": 4259}),
+        ];
+        assert!(result_sets_match(&items, &expected).is_ok());
+    }
+
+    #[test]
+    fn result_sets_pair_each_duplicate_row_once() {
+        test_support::init();
+        let items = [json!({"name": "Germany"}), json!({"name": "Germany"})];
+        let items: Vec<&Value> = items.iter().collect();
+
+        assert_eq!(
+            result_sets_match(&items, &[json!({"name": "Germany"})]),
+            Err(r#"unexpected results {"name":"Germany"}"#.to_string())
+        );
+        assert_eq!(
+            result_sets_match(
+                &items[..1],
+                &[json!({"name": "Germany"}), json!({"name": "Germany"})]
+            ),
+            Err("the results do not match the expected ones one to one".to_string())
+        );
+        assert_eq!(
+            result_sets_match(
+                &items,
+                &[json!({"name": "Germany"}), json!({"name": "France"})]
+            ),
+            Err(r#"no result matches {"name":"France"}"#.to_string())
+        );
+    }
+}
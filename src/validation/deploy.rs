@@ -0,0 +1,609 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use tokio::sync::mpsc;
+use tokio::time::{Instant, MissedTickBehavior, interval, sleep_until};
+
+use crate::config::config;
+use crate::helix_cli::{CliOutput, HelixCli, HelixCommand};
+use crate::project::project;
+use crate::ui::Spinner;
+use crate::validation::{
+    DeployReport, Diagnostic, HELPER_FILE, HelperFile, ParsedSchema, QueryValidator, SeedReference,
+    display_path, fnv1a, helper_queries, hx_files, load_deployment_state, parse_diagnostics,
+    save_deployment_state,
+};
+
+const READY_TIMEOUT: Duration = Duration::from_secs(60);
+const STATUS_RUNNING: &str = "running";
+const STATUS_FAILED: &str = "failed";
+
+static DEPLOY_RUNNING: AtomicBool = AtomicBool::new(false);
+static CANCEL_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+enum StepOutcome {
+    Finished(io::Result<CliOutput>),
+    TimedOut(Vec<String>),
+    Cancelled(Vec<String>),
+}
+
+/// Raises `DEPLOY_RUNNING` until dropped, so Ctrl-C cancels the deploy
+/// rather than exiting the tutorial. A deploy nested in another leaves the
+/// flags to the outer one.
+struct RunningDeploy {
+    outermost: bool,
+}
+
+impl RunningDeploy {
+    fn start() -> Self {
+        let outermost = !DEPLOY_RUNNING.swap(true, Ordering::SeqCst);
+        if outermost {
+            CANCEL_REQUESTED.store(false, Ordering::SeqCst);
+        }
+        Self { outermost }
+    }
+}
+
+impl Drop for RunningDeploy {
+    fn drop(&mut self) {
+        if self.outermost {
+            DEPLOY_RUNNING.store(false, Ordering::SeqCst);
+        }
+    }
+}
+
+/// Resolves once the learner has pressed Ctrl-C during the running deploy.
+async fn cancellation() {
+    let mut ticker = interval(Duration::from_millis(100));
+    while !CANCEL_REQUESTED.load(Ordering::SeqCst) {
+        ticker.tick().await;
+    }
+}
+
+/// Handles Ctrl-C for the lifetime of the runtime: while a deploy is running it
+/// cancels the deploy, otherwise it exits the tutorial as usual.
+pub async fn watch_interrupts() {
+    while tokio::signal::ctrl_c().await.is_ok() {
+        if DEPLOY_RUNNING.load(Ordering::SeqCst) {
+            CANCEL_REQUESTED.store(true, Ordering::SeqCst);
+        } else {
+            std::process::exit(130);
+        }
+    }
+}
+
+/// Deploys only when the .hx sources differ from the last successful deploy
+/// or the instance is no longer reachable. helixir's helper queries for the
+/// learner's schema, and the seed queries from `seed`, are deployed alongside
+/// their own.
+pub async fn deploy_if_changed(
+    cli: &dyn HelixCli,
+    timeout: Duration,
+    seed: Option<&SeedReference<'_>>,
+) -> DeployReport {
+    let _running = RunningDeploy::start();
+    let queries_dir = &project().queries_dir;
+    let helpers = ParsedSchema::from_dir(queries_dir)
+        .ok()
+        .map(|schema| helper_queries(&schema, seed));
+
+    let mut source_hashes = hash_sources(queries_dir);
+    if let Some(helpers) = &helpers {
+        source_hashes.insert(
+            HELPER_FILE.to_string(),
+            format!("{:016x}", fnv1a(helpers.as_bytes())),
+        );
+    }
+
+    let unchanged = load_deployment_state().is_some_and(|(deployed_hashes, status)| {
+        status == STATUS_RUNNING && deployed_hashes == source_hashes
+    });
+    if unchanged {
+        if endpoint_reachable(&config().endpoint) {
+            return DeployReport {
+                success: true,
+                cancelled: false,
+                skipped: true,
+                diagnostics: Vec::new(),
+                log: "No changes to the .hx files since the last deploy, skipped rebuilding.\n"
+                    .to_string(),
+            };
+        }
+        if let Some(report) = restart_instance(cli).await {
+            return report;
+        }
+    }
+
+    // removed again when dropped at the end of the deploy
+    let _helper_file = helpers.and_then(|helpers| HelperFile::write(queries_dir, &helpers).ok());
+    let mut report = redeploy_instance(cli, timeout).await;
+    if report.success
+        && !QueryValidator::new()
+            .wait_until_ready(READY_TIMEOUT, cancellation())
+            .await
+    {
+        report.success = false;
+        if CANCEL_REQUESTED.load(Ordering::SeqCst) {
+            report
+                .log
+                .push_str("cancelled while waiting for the instance\n");
+            report.cancelled = true;
+        } else {
+            report.diagnostics.push(
+                Diagnostic::new(format!(
+                    "The instance at {} did not respond within {}s of being pushed",
+                    config().endpoint,
+                    READY_TIMEOUT.as_secs()
+                ))
+                .with_suggestion("Press 'l' to see what 'helix status' reports for the instance"),
+            );
+            if let Ok(status) = cli.status().await {
+                log_command(&mut report.log, cli, HelixCommand::Status, &status);
+            }
+        }
+    }
+    if !report.cancelled {
+        let status = if report.success {
+            STATUS_RUNNING
+        } else {
+            STATUS_FAILED
+        };
+        let _ = save_deployment_state(&source_hashes, status);
+    }
+    report
+}
+
+pub async fn redeploy_instance(cli: &dyn HelixCli, timeout: Duration) -> DeployReport {
+    let deadline = Instant::now() + timeout;
+    let _running = RunningDeploy::start();
+
+    let mut report = DeployReport {
+        success: false,
+        cancelled: false,
+        skipped: false,
+        diagnostics: Vec::new(),
+        log: String::new(),
+    };
+
+    for command in [HelixCommand::Build, HelixCommand::Push] {
+        let known_diagnostics = report.diagnostics.len();
+        let log_start = report.log.len();
+        let mut succeeded = run_deploy_step(cli, command, deadline, timeout, &mut report).await;
+
+        // an instance left over from an earlier push still holds the port
+        if !succeeded
+            && command == HelixCommand::Push
+            && report.log[log_start..]
+                .to_lowercase()
+                .contains("address already in use")
+            && stop_instance(cli, &mut report).await
+        {
+            report.diagnostics.truncate(known_diagnostics);
+            succeeded = run_deploy_step(cli, command, deadline, timeout, &mut report).await;
+        }
+
+        if !succeeded {
+            return report;
+        }
+    }
+
+    report.success = true;
+    report
+}
+
+/// Starts an instance whose sources have not changed since it was deployed,
+/// which is much quicker than building it again. Returns `None` when it does
+/// not come up, so the caller can fall back to a full deploy, and a cancelled
+/// report when the learner presses Ctrl-C.
+async fn restart_instance(cli: &dyn HelixCli) -> Option<DeployReport> {
+    let output = cli.start().await.ok().filter(|output| output.success)?;
+    let mut log = String::new();
+    log_command(&mut log, cli, HelixCommand::Start, &output);
+
+    if !QueryValidator::new()
+        .wait_until_ready(READY_TIMEOUT, cancellation())
+        .await
+    {
+        if !CANCEL_REQUESTED.load(Ordering::SeqCst) {
+            return None;
+        }
+        log.push_str("cancelled\n");
+        return Some(DeployReport {
+            success: false,
+            cancelled: true,
+            skipped: false,
+            diagnostics: Vec::new(),
+            log,
+        });
+    }
+
+    log.push_str("No changes to the .hx files since the last deploy, restarted the instance.\n");
+    Some(DeployReport {
+        success: true,
+        cancelled: false,
+        skipped: true,
+        diagnostics: Vec::new(),
+        log,
+    })
+}
+
+/// Stops the running instance so a push can take over its port.
+async fn stop_instance(cli: &dyn HelixCli, report: &mut DeployReport) -> bool {
+    match cli.stop().await {
+        Ok(output) => {
+            log_command(&mut report.log, cli, HelixCommand::Stop, &output);
+            output.success
+        }
+        Err(_) => false,
+    }
+}
+
+fn log_command(log: &mut String, cli: &dyn HelixCli, command: HelixCommand, output: &CliOutput) {
+    log.push_str(&format!("$ {}\n", cli.command_line(command)));
+    log.push_str(&output.stdout);
+    log.push_str(&output.stderr);
+}
+
+async fn run_deploy_step(
+    cli: &dyn HelixCli,
+    command: HelixCommand,
+    deadline: Instant,
+    timeout: Duration,
+    report: &mut DeployReport,
+) -> bool {
+    let command_line = cli.command_line(command);
+    report.log.push_str(&format!("$ {}\n", command_line));
+
+    let outcome = run_with_spinner(cli, command, &command_line, deadline).await;
+    let output = match outcome {
+        StepOutcome::Finished(output) => output,
+        StepOutcome::TimedOut(partial) => {
+            append_lines(&mut report.log, &partial);
+            report.log.push_str("timed out\n");
+            report.diagnostics.push(
+                Diagnostic::new(format!(
+                    "'{}' timed out after {}s",
+                    command_line,
+                    timeout.as_secs()
+                ))
+                .with_suggestion(
+                    "Raise the timeout with --deploy-timeout or HELIXIR_DEPLOY_TIMEOUT if your builds are slow",
+                ),
+            );
+            return false;
+        }
+        StepOutcome::Cancelled(partial) => {
+            append_lines(&mut report.log, &partial);
+            report.log.push_str("cancelled\n");
+            report.cancelled = true;
+            return false;
+        }
+    };
+
+    let result = match output {
+        Ok(result) => result,
+        Err(e) => {
+            report.log.push_str(&format!("{}\n", e));
+            report.diagnostics.push(
+                Diagnostic::new(format!("Could not run '{}': {}", command_line, e))
+                    .with_suggestion(
+                        "Install the Helix CLI and make sure it is on your PATH, or point --helix-bin at it",
+                    ),
+            );
+            return false;
+        }
+    };
+
+    report.log.push_str(&result.stdout);
+    report.log.push_str(&result.stderr);
+
+    let combined = format!("{}\n{}", result.stdout, result.stderr);
+    let step_diagnostics = parse_diagnostics(&combined);
+    let failed =
+        !result.success || combined.contains("Parse error") || combined.contains("Error compiling");
+
+    if failed && step_diagnostics.is_empty() {
+        report.diagnostics.push(Diagnostic::new(format!(
+            "'{}' failed with exit code: {:?}",
+            command_line, result.code
+        )));
+    }
+    report.diagnostics.extend(step_diagnostics);
+
+    !failed
+}
+
+/// Runs one helix command while animating a spinner with the tail of its
+/// output. Dropping the command future on timeout or cancellation kills it.
+async fn run_with_spinner(
+    cli: &dyn HelixCli,
+    command: HelixCommand,
+    command_line: &str,
+    deadline: Instant,
+) -> StepOutcome {
+    let (sender, mut receiver) = mpsc::unbounded_channel::<String>();
+    let mut on_line = move |line: &str| {
+        let _ = sender.send(line.to_string());
+    };
+
+    let step = async {
+        match command {
+            HelixCommand::Build => cli.build(&mut on_line).await,
+            HelixCommand::Push => cli.push(&mut on_line).await,
+            other => cli.run(other, &mut on_line).await,
+        }
+    };
+    tokio::pin!(step);
+
+    let mut spinner = Spinner::new(format!("Running '{}'", command_line));
+    let mut ticker = interval(Duration::from_millis(100));
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    let mut captured = Vec::new();
+
+    let outcome = loop {
+        tokio::select! {
+            output = &mut step => break StepOutcome::Finished(output),
+            Some(line) = receiver.recv() => {
+                spinner.push_line(&line);
+                captured.push(line);
+            }
+            _ = ticker.tick() => {
+                if CANCEL_REQUESTED.load(Ordering::SeqCst) {
+                    break StepOutcome::Cancelled(captured);
+                }
+                spinner.tick();
+            }
+            _ = sleep_until(deadline) => break StepOutcome::TimedOut(captured),
+        }
+    };
+
+    spinner.finish();
+    outcome
+}
+
+fn append_lines(log: &mut String, lines: &[String]) {
+    for line in lines {
+        log.push_str(line);
+        log.push('\n');
+    }
+}
+
+/// FNV-1a hashes of every .hx file in `dir`, keyed by path relative to the
+/// project root. The hash is stable across Rust versions, unlike
+/// `DefaultHasher`, since it is persisted.
+pub fn hash_sources(dir: &Path) -> BTreeMap<String, String> {
+    hx_files(dir)
+        .into_iter()
+        .filter_map(|path| {
+            let content = std::fs::read(&path).ok()?;
+            Some((display_path(&path), format!("{:016x}", fnv1a(&content))))
+        })
+        .collect()
+}
+
+/// Whether something is accepting TCP connections at the endpoint's host and port.
+pub fn endpoint_reachable(endpoint: &str) -> bool {
+    let (default_port, rest) = match endpoint.strip_prefix("https://") {
+        Some(rest) => (443, rest),
+        None => (80, endpoint.trim_start_matches("http://")),
+    };
+    let host = rest.split('/').next().unwrap_or_default();
+    let address = if host
+        .rsplit_once(':')
+        .is_some_and(|(_, port)| port.parse::<u16>().is_ok())
+    {
+        host.to_string()
+    } else {
+        format!("{}:{}", host, default_port)
+    };
+    let Ok(addresses) = address.to_socket_addrs() else {
+        return false;
+    };
+
+    addresses
+        .into_iter()
+        .any(|address| TcpStream::connect_timeout(&address, Duration::from_millis(500)).is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tokio::sync::{Mutex, MutexGuard};
+
+    use super::*;
+    use crate::helix_cli::FakeHelixCli;
+    use crate::test_support::{self, INSTANCE_STARTING};
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    const BUILD_ERROR: &str = "\
+error[E101]: Node type `Contnent` is not in schema
+  --> queries.hx:3:12
+";
+
+    /// Deploys share the project's progress file and the cancellation flags,
+    /// so the tests take turns.
+    static DEPLOYS: Mutex<()> = Mutex::const_new(());
+
+    /// Starts a test from a project that has never been deployed.
+    async fn fresh_project() -> MutexGuard<'static, ()> {
+        let guard = DEPLOYS.lock().await;
+        test_support::init();
+        let _ = fs::remove_file(project().progress_file());
+        guard
+    }
+
+    fn succeeding() -> FakeHelixCli {
+        FakeHelixCli::new()
+            .succeed(HelixCommand::Build, "Compiled queries\n")
+            .succeed(HelixCommand::Push, "Instance dev started\n")
+    }
+
+    #[tokio::test]
+    async fn redeploy_runs_build_then_push() {
+        let _guard = fresh_project().await;
+        let cli = succeeding();
+
+        let report = redeploy_instance(&cli, TIMEOUT).await;
+
+        assert!(report.success);
+        assert!(report.diagnostics.is_empty());
+        assert_eq!(cli.calls(), [HelixCommand::Build, HelixCommand::Push]);
+        assert!(report.log.contains("$ helix build dev\nCompiled queries\n"));
+        assert!(
+            report
+                .log
+                .contains("$ helix push dev\nInstance dev started\n")
+        );
+    }
+
+    #[tokio::test]
+    async fn redeploy_stops_at_build_errors_and_reports_them() {
+        let _guard = fresh_project().await;
+        let cli = FakeHelixCli::new().fail(HelixCommand::Build, BUILD_ERROR);
+
+        let report = redeploy_instance(&cli, TIMEOUT).await;
+
+        assert!(!report.success);
+        assert_eq!(cli.calls(), [HelixCommand::Build]);
+        let [diagnostic] = report.diagnostics.as_slice() else {
+            panic!("expected one diagnostic, got {:?}", report.diagnostics);
+        };
+        assert_eq!(diagnostic.file.as_deref(), Some("queries.hx"));
+        assert_eq!(diagnostic.line, Some(3));
+        assert_eq!(diagnostic.message, "Node type `Contnent` is not in schema");
+        assert!(diagnostic.suggestion.as_deref().unwrap().contains("N::"));
+    }
+
+    #[tokio::test]
+    async fn redeploy_reports_a_missing_helix_binary() {
+        let _guard = fresh_project().await;
+        let cli = FakeHelixCli::new().missing(HelixCommand::Build);
+
+        let report = redeploy_instance(&cli, TIMEOUT).await;
+
+        assert!(!report.success);
+        let [diagnostic] = report.diagnostics.as_slice() else {
+            panic!("expected one diagnostic, got {:?}", report.diagnostics);
+        };
+        assert!(
+            diagnostic
+                .message
+                .starts_with("Could not run 'helix build dev'")
+        );
+        assert!(diagnostic.suggestion.as_deref().unwrap().contains("PATH"));
+    }
+
+    #[tokio::test]
+    async fn redeploy_stops_an_instance_holding_the_port() {
+        let _guard = fresh_project().await;
+        let cli = FakeHelixCli::new()
+            .succeed(HelixCommand::Build, "")
+            .fail(
+                HelixCommand::Push,
+                "Error: Address already in use (os error 98)\n",
+            )
+            .succeed(HelixCommand::Stop, "Stopped instance dev\n")
+            .succeed(HelixCommand::Push, "");
+
+        let report = redeploy_instance(&cli, TIMEOUT).await;
+
+        assert!(report.success, "{:?}", report.diagnostics);
+        assert!(report.diagnostics.is_empty());
+        assert_eq!(
+            cli.calls(),
+            [
+                HelixCommand::Build,
+                HelixCommand::Push,
+                HelixCommand::Stop,
+                HelixCommand::Push
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn deploy_skips_unchanged_sources() {
+        let _guard = fresh_project().await;
+
+        let report = deploy_if_changed(&succeeding(), TIMEOUT, None).await;
+        assert!(report.success && !report.skipped);
+
+        // nothing is scripted, so any helix command would fail
+        let cli = FakeHelixCli::new();
+        let report = deploy_if_changed(&cli, TIMEOUT, None).await;
+        assert!(report.success && report.skipped);
+        assert!(cli.calls().is_empty());
+    }
+
+    #[tokio::test]
+    async fn deploy_reruns_after_an_edit() {
+        let _guard = fresh_project().await;
+        let queries = project().queries_dir.join("queries.hx");
+
+        let report = deploy_if_changed(&succeeding(), TIMEOUT, None).await;
+        assert!(report.success && !report.skipped);
+
+        let mut content = fs::read_to_string(&queries).unwrap();
+        content.push_str("// edited\n");
+        fs::write(&queries, &content).unwrap();
+
+        let cli = succeeding();
+        let report = deploy_if_changed(&cli, TIMEOUT, None).await;
+        assert!(report.success && !report.skipped);
+        assert_eq!(cli.calls(), [HelixCommand::Build, HelixCommand::Push]);
+
+        fs::write(&queries, content.trim_end_matches("// edited\n")).unwrap();
+    }
+
+    #[tokio::test]
+    async fn deploy_reruns_after_a_failed_build() {
+        let _guard = fresh_project().await;
+
+        let cli = FakeHelixCli::new().fail(HelixCommand::Build, BUILD_ERROR);
+        let report = deploy_if_changed(&cli, TIMEOUT, None).await;
+        assert!(!report.success);
+        assert_eq!(report.diagnostics[0].line, Some(3));
+        assert!(!project().queries_dir.join(HELPER_FILE).exists());
+
+        let cli = succeeding();
+        let report = deploy_if_changed(&cli, TIMEOUT, None).await;
+        assert!(report.success && !report.skipped);
+        assert_eq!(cli.calls(), [HelixCommand::Build, HelixCommand::Push]);
+    }
+
+    #[tokio::test]
+    async fn deploy_cancels_while_waiting_for_the_instance() {
+        let _guard = fresh_project().await;
+        INSTANCE_STARTING.store(true, Ordering::SeqCst);
+
+        let press_ctrl_c = async {
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            // the readiness wait is part of the deploy, so Ctrl-C cancels it
+            assert!(DEPLOY_RUNNING.load(Ordering::SeqCst));
+            CANCEL_REQUESTED.store(true, Ordering::SeqCst);
+        };
+        let cli = succeeding();
+        let (report, ()) = tokio::join!(deploy_if_changed(&cli, TIMEOUT, None), press_ctrl_c);
+        INSTANCE_STARTING.store(false, Ordering::SeqCst);
+
+        assert!(report.cancelled && !report.success);
+        assert!(report.diagnostics.is_empty());
+        assert!(
+            report
+                .log
+                .ends_with("cancelled while waiting for the instance\n")
+        );
+        assert!(!DEPLOY_RUNNING.load(Ordering::SeqCst));
+
+        // the push was never confirmed, so the next deploy runs again
+        let cli = succeeding();
+        let report = deploy_if_changed(&cli, TIMEOUT, None).await;
+        assert!(report.success && !report.skipped);
+        assert_eq!(cli.calls(), [HelixCommand::Build, HelixCommand::Push]);
+    }
+}
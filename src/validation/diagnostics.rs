@@ -0,0 +1,256 @@
+use std::{fmt, sync::LazyLock};
+
+use regex::Regex;
+
+use crate::validation::Diagnostic;
+
+impl Diagnostic {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            file: None,
+            line: None,
+            message: message.into(),
+            suggestion: None,
+        }
+    }
+
+    pub fn with_suggestion(mut self, suggestion: impl Into<String>) -> Self {
+        self.suggestion = Some(suggestion.into());
+        self
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.file, self.line) {
+            (Some(file), Some(line)) => write!(f, "{}:{}: {}", file, line, self.message),
+            (Some(file), None) => write!(f, "{}: {}", file, self.message),
+            (None, Some(line)) => write!(f, "line {}: {}", line, self.message),
+            (None, None) => write!(f, "{}", self.message),
+        }
+    }
+}
+
+static ANSI: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\x1b\[[0-9;]*[A-Za-z]").expect("ANSI pattern is valid"));
+
+/// `error[E101]: message`, with or without the code.
+static HEADER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?i:error)(?:\[(E\d+)\])?:\s*(.*)$").expect("header pattern is valid")
+});
+
+/// `--> file:line:col`, where the file is optional.
+static LOCATION: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"-->\s*(?:([^\s:]*[A-Za-z_./][^\s:]*):)?(\d+)(?::\d+)?\s*$")
+        .expect("location pattern is valid")
+});
+
+static HELP: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^=?\s*(?:help|note):\s*(.+)$").expect("help pattern is valid"));
+
+static EXPECTED: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^=\s*(expected .+)$").expect("expected pattern is valid"));
+
+/// Parses the output of `helix build` / `helix push` into structured diagnostics.
+///
+/// Understands the rustc-style reports emitted by the helix compiler
+/// (`error[E101]: ...` followed by `--> file:line:col` and `= help: ...`)
+/// as well as the plain `Parse error` and `Error compiling` messages.
+pub fn parse_diagnostics(output: &str) -> Vec<Diagnostic> {
+    let clean = ANSI.replace_all(output, "");
+    let mut diagnostics = Vec::new();
+    let mut current: Option<(Option<String>, Diagnostic)> = None;
+
+    for raw_line in clean.lines() {
+        let line = raw_line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let starts_diagnostic = HEADER.is_match(line)
+            || line.contains("Parse error")
+            || line.contains("Error compiling");
+        if starts_diagnostic {
+            if let Some(finished) = current.take() {
+                diagnostics.push(finished);
+            }
+
+            let (code, message) = match HEADER.captures(line) {
+                Some(caps) => (
+                    caps.get(1).map(|m| m.as_str().to_string()),
+                    caps.get(2).map_or("", |m| m.as_str()).to_string(),
+                ),
+                None => (None, line.to_string()),
+            };
+            let message = LOCATION
+                .replace(&message, "")
+                .trim()
+                .trim_end_matches(':')
+                .to_string();
+            let message = if message.is_empty() {
+                line.to_string()
+            } else {
+                message
+            };
+            current = Some((code, Diagnostic::new(message)));
+        }
+
+        let Some((_, diagnostic)) = current.as_mut() else {
+            continue;
+        };
+
+        if let Some(caps) = LOCATION.captures(line) {
+            if diagnostic.line.is_none() {
+                diagnostic.file = caps.get(1).map(|m| m.as_str().to_string());
+                diagnostic.line = caps[2].parse().ok();
+            }
+        } else if let Some(caps) = HELP.captures(line) {
+            if diagnostic.suggestion.is_none() {
+                diagnostic.suggestion = Some(caps[1].trim().to_string());
+            }
+        } else if let Some(caps) = EXPECTED.captures(line) {
+            diagnostic.message = format!("{} ({})", diagnostic.message, &caps[1]);
+        }
+    }
+
+    if let Some(finished) = current.take() {
+        diagnostics.push(finished);
+    }
+
+    diagnostics
+        .into_iter()
+        .map(|(code, mut diagnostic)| {
+            if diagnostic.suggestion.is_none() {
+                diagnostic.suggestion = known_suggestion(code.as_deref(), &diagnostic.message);
+            }
+            diagnostic
+        })
+        .collect()
+}
+
+fn known_suggestion(code: Option<&str>, message: &str) -> Option<String> {
+    let message = message.to_lowercase();
+    let suggestion = match code {
+        Some("E101") => "Define the node type in schema.hx with N:: and check its spelling",
+        Some("E102") => "Define the edge type in schema.hx with E:: and check its spelling",
+        Some("E103") => "Define the vector type in schema.hx with V:: and check its spelling",
+        Some("E202") => "Check that the property name matches the one in your schema",
+        Some("E301") => "Assign the variable with `<-` before using it",
+        _ if message.contains("parse error") || message.contains("expected") => {
+            "Check the syntax on this line: each QUERY needs `=>` and a RETURN, and every bracket must be closed"
+        }
+        _ if message.contains("not in schema") || message.contains("unknown") => {
+            "Make sure every type you use is defined in schema.hx and spelled the same way"
+        }
+        _ if message.contains("not in scope") || message.contains("undefined") => {
+            "Assign the variable with `<-` before using it"
+        }
+        _ if message.contains("error compiling") => {
+            "Check that your query parameter types match the schema (String, I64, F64, ID, [F64])"
+        }
+        _ if message.contains("docker") => "Make sure Docker is installed and running",
+        _ if message.contains("address already in use") => {
+            "Another instance is using the port; stop it with 'helix stop' and try again"
+        }
+        _ => return None,
+    };
+    Some(suggestion.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `helix build dev` with a misspelled node type, colours included.
+    const UNKNOWN_NODE: &str = "\
+\x1b[1m\x1b[32m✓\x1b[0m Loaded 2 .hx files
+\x1b[1m\x1b[91merror[E101]\x1b[0m\x1b[1m: `Contnent` is not a valid node type\x1b[0m
+  \x1b[1m\x1b[94m-->\x1b[0m queries.hx:3:20
+   \x1b[1m\x1b[94m|\x1b[0m
+ \x1b[1m\x1b[94m3\x1b[0m \x1b[1m\x1b[94m|\x1b[0m     continents <- N<Contnent>
+   \x1b[1m\x1b[94m|\x1b[0m                     \x1b[1m\x1b[91m^^^^^^^^\x1b[0m
+   \x1b[1m\x1b[94m|\x1b[0m
+   \x1b[1m\x1b[94m=\x1b[0m \x1b[1mhelp\x1b[0m: check the node type name or add it to the schema
+";
+
+    /// A query missing its RETURN, as reported by the parser.
+    const PARSE_ERROR: &str = "\
+Error: Parse error:  --> 4:1
+  |
+4 | QUERY getCountry(country_id: ID) =>
+  | ^---
+  |
+  = expected return_stmt
+";
+
+    const TWO_ERRORS: &str = "\
+error[E202]: `populaton` is not a valid property of `Country`
+  --> queries.hx:7:31
+error[E301]: variable `country` is not in scope
+  --> queries.hx:12:12
+";
+
+    #[test]
+    fn reads_file_line_and_message_from_compiler_errors() {
+        let [diagnostic] = parse_diagnostics(UNKNOWN_NODE).try_into().unwrap();
+
+        assert_eq!(diagnostic.file.as_deref(), Some("queries.hx"));
+        assert_eq!(diagnostic.line, Some(3));
+        assert_eq!(diagnostic.message, "`Contnent` is not a valid node type");
+        assert_eq!(
+            diagnostic.suggestion.as_deref(),
+            Some("check the node type name or add it to the schema")
+        );
+    }
+
+    #[test]
+    fn reads_parse_errors_without_a_file() {
+        let [diagnostic] = parse_diagnostics(PARSE_ERROR).try_into().unwrap();
+
+        assert_eq!(diagnostic.file, None);
+        assert_eq!(diagnostic.line, Some(4));
+        assert_eq!(diagnostic.message, "Parse error (expected return_stmt)");
+        assert!(diagnostic.suggestion.unwrap().contains("RETURN"));
+    }
+
+    #[test]
+    fn keeps_each_error_with_its_own_location() {
+        let diagnostics = parse_diagnostics(TWO_ERRORS);
+
+        let locations: Vec<_> = diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.line, diagnostic.message.as_str()))
+            .collect();
+        assert_eq!(
+            locations,
+            [
+                (Some(7), "`populaton` is not a valid property of `Country`"),
+                (Some(12), "variable `country` is not in scope"),
+            ]
+        );
+        assert!(diagnostics[1].suggestion.as_deref().unwrap().contains("<-"));
+    }
+
+    #[test]
+    fn leaves_lines_in_other_formats_unparsed() {
+        let output = "\
+Compiling queries for instance dev
+warning: query `getCity` is never used
+error in queries.hx at line 3
+";
+
+        assert!(parse_diagnostics(output).is_empty());
+    }
+
+    #[test]
+    fn ignores_locations_in_other_formats() {
+        let output = "\
+error: failed to build queries
+  at queries.hx line 3
+";
+
+        let [diagnostic] = parse_diagnostics(output).try_into().unwrap();
+        assert_eq!(diagnostic.message, "failed to build queries");
+        assert_eq!((diagnostic.file, diagnostic.line), (None, None));
+    }
+}
@@ -0,0 +1,69 @@
+use serde_json::json;
+
+use crate::validation::fnv1a;
+
+/// The length of the generated vectors, the same as the literal vectors the
+/// lesson data used before.
+pub const EMBEDDING_DIMENSIONS: usize = 384;
+
+const NGRAM_LENGTH: usize = 3;
+const WORD_WEIGHT: f64 = 2.0;
+const TEMPLATE_START: &str = "{{embed:\"";
+const TEMPLATE_END: &str = "\"}}";
+
+/// A deterministic, offline stand-in for an embedding model. The words of
+/// `text` and their character trigrams are hashed into a fixed number of
+/// dimensions, so texts that share words and word parts end up close by
+/// cosine distance. The result has unit length.
+pub fn embed(text: &str) -> Vec<f64> {
+    let mut vector = vec![0.0; EMBEDDING_DIMENSIONS];
+    let text = text.to_lowercase();
+
+    for word in text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+    {
+        add_feature(&mut vector, word, WORD_WEIGHT);
+
+        // padded so trigrams also mark where words start and end
+        let padded: Vec<char> = format!(" {} ", word).chars().collect();
+        for ngram in padded.windows(NGRAM_LENGTH) {
+            add_feature(&mut vector, &ngram.iter().collect::<String>(), 1.0);
+        }
+    }
+
+    let norm = vector.iter().map(|x| x * x).sum::<f64>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|x| *x /= norm);
+    }
+    vector
+}
+
+/// Replaces every `{{embed:"text"}}` string in lesson data with the
+/// embedding of its text.
+pub fn expand_embeddings(value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::String(template) => match template
+            .strip_prefix(TEMPLATE_START)
+            .and_then(|rest| rest.strip_suffix(TEMPLATE_END))
+        {
+            Some(text) => json!(embed(text)),
+            None => serde_json::Value::String(template),
+        },
+        serde_json::Value::Array(items) => items.into_iter().map(expand_embeddings).collect(),
+        serde_json::Value::Object(fields) => fields
+            .into_iter()
+            .map(|(key, value)| (key, expand_embeddings(value)))
+            .collect(),
+        other => other,
+    }
+}
+
+/// Signed feature hashing: the sign bit keeps colliding features from always
+/// adding up.
+fn add_feature(vector: &mut [f64], feature: &str, weight: f64) {
+    let hash = fnv1a(feature.as_bytes());
+    let index = (hash % EMBEDDING_DIMENSIONS as u64) as usize;
+    let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+    vector[index] += sign * weight;
+}
@@ -1,23 +1,59 @@
+use crate::config::config;
+use crate::formatter::HelixFormatter;
 use crate::lesson_types::*;
-use crate::validation::{QueryValidator, get_latest_entity_id, save_created_entity, load_instance_data};
-use helix_rs::{HelixDB, HelixDBClient};
-use serde_json::json;
+use crate::project::project;
+use crate::ui::Spinner;
+use crate::validation::{
+    ParsedSchema, QueryValidator, SEED_QUERY_PREFIX, compare_ranking, describe_shape_mismatches,
+    embed, expand_embeddings, find_created_entity, floats_match, get_latest_entity_id,
+    load_instance_data, nearest_cities, prune_created_entities, replace_created_entity,
+    vectors_match,
+};
+use helix_rs::{HelixDB, HelixDBClient, HelixError};
 use serde::{Serialize, de::DeserializeOwned};
-use std::collections::HashMap;
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
+use std::time::Duration;
+use tokio::time::{Instant, sleep, timeout};
+
+const MAX_QUERY_ATTEMPTS: u32 = 5;
+const INITIAL_RETRY_DELAY: Duration = Duration::from_millis(200);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(2);
+const READINESS_PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+/// Queried only to see whether the server answers at all; it does not need to exist.
+const READINESS_PROBE_QUERY: &str = "__helixir_readiness_probe";
 
 #[derive(Debug)]
-enum EntityType {
+pub(super) enum EntityType {
     Continent,
     Country,
     City,
 }
 
 impl EntityType {
+    pub(super) fn from_storage_key(key: &str) -> Option<Self> {
+        match key {
+            "continents" => Some(Self::Continent),
+            "countries" => Some(Self::Country),
+            "cities" => Some(Self::City),
+            _ => None,
+        }
+    }
+
+    pub(super) fn label(&self) -> &'static str {
+        match self {
+            Self::Continent => "Continent",
+            Self::Country => "Country",
+            Self::City => "City",
+        }
+    }
+
     #[allow(dead_code)]
     fn storage_key(&self) -> &'static str {
         match self {
             Self::Continent => "continents",
-            Self::Country => "countries", 
+            Self::Country => "countries",
             Self::City => "cities",
         }
     }
@@ -25,7 +61,9 @@ impl EntityType {
     #[allow(dead_code)]
     fn dependency_error(&self) -> &'static str {
         match self {
-            Self::Continent => "No continent found. Please run lesson 5 first to create a continent.",
+            Self::Continent => {
+                "No continent found. Please run lesson 5 first to create a continent."
+            }
             Self::Country => "No country found. Please run lesson 6 first to create a country.",
             Self::City => "No city found. Please run lesson 7 first to create a city.",
         }
@@ -41,13 +79,35 @@ impl EntityType {
     }
 }
 
+/// Where a create check records the node it created, and the edge from its
+/// parent that the create query should have added, if it has one.
+struct CreatedNode<'a> {
+    storage_key: &'a str,
+    parent_edge: Option<(&'a str, &'a str)>,
+}
+
 impl QueryValidator {
     pub fn new() -> Self {
         Self {
-            client: HelixDB::new(Some("http://localhost:6969"), None, None),
+            client: HelixDB::new(Some(&config().endpoint), None, config().api_key.as_deref()),
+            query_prefix: "",
+            schema: OnceLock::new(),
+        }
+    }
+
+    /// Runs lesson queries against helixir's reference copies, deployed by
+    /// the helper file, rather than the learner's own.
+    pub fn seeding() -> Self {
+        Self {
+            query_prefix: SEED_QUERY_PREFIX,
+            ..Self::new()
         }
     }
 
+    fn lesson_query_name(&self, query_name: &str) -> String {
+        format!("{}{}", self.query_prefix, query_name)
+    }
+
     fn get_entity_mapping_for_lesson6() -> HashMap<&'static str, &'static str> {
         let mut mapping = HashMap::new();
         mapping.insert("London", "United Kingdom");
@@ -57,7 +117,6 @@ impl QueryValidator {
         mapping
     }
 
-
     fn get_city_id_by_name(&self, city_name: &str) -> Option<String> {
         let instance_data = load_instance_data();
         if let Some(cities) = instance_data["created_entities"]["cities"].as_array() {
@@ -86,72 +145,58 @@ impl QueryValidator {
         None
     }
 
-    fn resolve_entity_id(&self, placeholder: &str, entity_type: &str) -> Result<String, anyhow::Error> {
+    fn resolve_entity_id(
+        &self,
+        placeholder: &str,
+        entity_type: &str,
+    ) -> Result<String, anyhow::Error> {
         match placeholder {
-            "europe_continent_id" | "some_continent_id" => {
-                self.get_continent_id_by_name("Europe").ok_or_else(|| {
-                    anyhow::anyhow!("Europe continent not found in instance data")
-                })
-            }
-            "uk_country_id" => {
-                self.get_country_id_by_name("United Kingdom").ok_or_else(|| {
-                    anyhow::anyhow!("United Kingdom not found in instance data")
-                })
-            }
-            "germany_country_id" => {
-                self.get_country_id_by_name("Germany").ok_or_else(|| {
-                    anyhow::anyhow!("Germany not found in instance data")
-                })
-            }
-            "some_country_id" => {
-                get_latest_entity_id("countries").ok_or_else(|| {
-                    anyhow::anyhow!("No country found. Please create a country first.")
-                })
-            }
-            "london_city_id" => {
-                self.get_city_id_by_name("London").ok_or_else(|| {
-                    anyhow::anyhow!("London not found in instance data")
-                })
-            }
-            "berlin_city_id" => {
-                self.get_city_id_by_name("Berlin").ok_or_else(|| {
-                    anyhow::anyhow!("Berlin not found in instance data")
-                })
-            }
-            "manchester_city_id" => {
-                self.get_city_id_by_name("Manchester").ok_or_else(|| {
-                    anyhow::anyhow!("Manchester not found in instance data")
-                })
-            }
-            "hamburg_city_id" => {
-                self.get_city_id_by_name("Hamburg").ok_or_else(|| {
-                    anyhow::anyhow!("Hamburg not found in instance data")
-                })
-            }
-            "ID" => {
-                get_latest_entity_id(entity_type).ok_or_else(|| {
-                    anyhow::anyhow!("No {} found. Please create one first.", entity_type)
-                })
-            }
-            _ => {
-                get_latest_entity_id(entity_type).ok_or_else(|| {
-                    anyhow::anyhow!("No {} found for placeholder '{}'", entity_type, placeholder)
-                })
-            }
+            "europe_continent_id" | "some_continent_id" => self
+                .get_continent_id_by_name("Europe")
+                .ok_or_else(|| anyhow::anyhow!("Europe continent not found in instance data")),
+            "uk_country_id" => self
+                .get_country_id_by_name("United Kingdom")
+                .ok_or_else(|| anyhow::anyhow!("United Kingdom not found in instance data")),
+            "germany_country_id" => self
+                .get_country_id_by_name("Germany")
+                .ok_or_else(|| anyhow::anyhow!("Germany not found in instance data")),
+            "some_country_id" => get_latest_entity_id("countries")
+                .ok_or_else(|| anyhow::anyhow!("No country found. Please create a country first.")),
+            "london_city_id" => self
+                .get_city_id_by_name("London")
+                .ok_or_else(|| anyhow::anyhow!("London not found in instance data")),
+            "berlin_city_id" => self
+                .get_city_id_by_name("Berlin")
+                .ok_or_else(|| anyhow::anyhow!("Berlin not found in instance data")),
+            "manchester_city_id" => self
+                .get_city_id_by_name("Manchester")
+                .ok_or_else(|| anyhow::anyhow!("Manchester not found in instance data")),
+            "hamburg_city_id" => self
+                .get_city_id_by_name("Hamburg")
+                .ok_or_else(|| anyhow::anyhow!("Hamburg not found in instance data")),
+            "ID" => get_latest_entity_id(entity_type).ok_or_else(|| {
+                anyhow::anyhow!("No {} found. Please create one first.", entity_type)
+            }),
+            _ => get_latest_entity_id(entity_type).ok_or_else(|| {
+                anyhow::anyhow!("No {} found for placeholder '{}'", entity_type, placeholder)
+            }),
         }
     }
 
-    fn replace_placeholder_ids(&self, mut input_obj: serde_json::Value) -> Result<serde_json::Value, anyhow::Error> {
+    pub(super) fn replace_placeholder_ids(
+        &self,
+        mut input_obj: serde_json::Value,
+    ) -> Result<serde_json::Value, anyhow::Error> {
         if let Some(placeholder) = input_obj["continent_id"].as_str() {
             let real_id = self.resolve_entity_id(placeholder, "continents")?;
             input_obj["continent_id"] = json!(real_id);
         }
-        
+
         if let Some(placeholder) = input_obj["country_id"].as_str() {
             let real_id = self.resolve_entity_id(placeholder, "countries")?;
             input_obj["country_id"] = json!(real_id);
         }
-        
+
         if let Some(placeholder) = input_obj["city_id"].as_str() {
             let real_id = self.resolve_entity_id(placeholder, "cities")?;
             input_obj["city_id"] = json!(real_id);
@@ -174,23 +219,120 @@ impl QueryValidator {
         None
     }
 
-    async fn execute_query<I, R>(&self, query_name: &str, input: &I) -> anyhow::Result<R>
+    pub(super) async fn execute_query<I, R>(&self, query_name: &str, input: &I) -> anyhow::Result<R>
     where
         I: Serialize + Sync,
         R: DeserializeOwned,
     {
-        self.client
-            .query(query_name, input)
+        let response: serde_json::Value = self
+            .query_with_retry(&self.lesson_query_name(query_name), input)
             .await
-            .map_err(|e| anyhow::anyhow!("Query failed: {}. Check your query name and syntax.", e))
+            .map_err(|e| {
+                anyhow::anyhow!("Query failed: {}. Check your query name and syntax.", e)
+            })?;
+
+        // unset properties only matter if the lesson reads them, which decoding tells
+        let mismatches = match self.schema() {
+            Some(schema) => schema.check_response_shape(&response),
+            None => Vec::new(),
+        };
+        if mismatches.iter().any(|m| !m.system && !m.unset) {
+            return Err(anyhow::anyhow!(describe_shape_mismatches(
+                query_name,
+                &mismatches
+            )));
+        }
+        serde_json::from_value(response).map_err(|e| {
+            if mismatches.is_empty() {
+                anyhow::anyhow!(
+                    "The response of {} is missing something the lesson reads: {}. Check the names in its RETURN statement.",
+                    query_name,
+                    e
+                )
+            } else {
+                anyhow::anyhow!(describe_shape_mismatches(query_name, &mismatches))
+            }
+        })
+    }
+
+    fn schema(&self) -> Option<&ParsedSchema> {
+        self.schema
+            .get_or_init(|| ParsedSchema::from_dir(&project().queries_dir).ok())
+            .as_ref()
+    }
+
+    /// Retries requests that never reached the server (e.g. connection refused
+    /// while the instance is starting). Errors the server answered with, and
+    /// failures after the request was sent, are returned straight away so a
+    /// mutation is never applied twice.
+    pub(super) async fn query_with_retry<I, R>(
+        &self,
+        query_name: &str,
+        input: &I,
+    ) -> Result<R, HelixError>
+    where
+        I: Serialize + Sync,
+        R: DeserializeOwned,
+    {
+        let mut delay = INITIAL_RETRY_DELAY;
+        let mut attempt = 1;
+
+        loop {
+            match self.client.query(query_name, input).await {
+                Err(HelixError::ReqwestError(e))
+                    if e.is_connect() && attempt < MAX_QUERY_ATTEMPTS =>
+                {
+                    sleep(delay).await;
+                    delay = (delay * 2).min(MAX_RETRY_DELAY);
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
     }
 
+    /// Polls the endpoint with backoff until the server answers any request,
+    /// `wait` has passed, or `cancel` resolves.
+    pub async fn wait_until_ready(&self, wait: Duration, cancel: impl Future<Output = ()>) -> bool {
+        let deadline = Instant::now() + wait;
+        let mut delay = INITIAL_RETRY_DELAY;
+        let mut spinner = Spinner::new("Waiting for the instance to accept queries");
+
+        let empty_input = json!({});
+
+        let poll = async {
+            loop {
+                let probe = self
+                    .client
+                    .query::<_, serde_json::Value>(READINESS_PROBE_QUERY, &empty_input);
+                match timeout(READINESS_PROBE_TIMEOUT, probe).await {
+                    Ok(Err(HelixError::ReqwestError(e))) if e.is_connect() || e.is_timeout() => {}
+                    Ok(_) => return true,
+                    Err(_) => {}
+                }
+
+                if Instant::now() + delay > deadline {
+                    return false;
+                }
+                spinner.tick();
+                sleep(delay).await;
+                delay = (delay * 2).min(MAX_RETRY_DELAY);
+            }
+        };
+        let ready = tokio::select! {
+            ready = poll => ready,
+            () = cancel => false,
+        };
+
+        spinner.finish();
+        ready
+    }
 
     async fn execute_create_query<I, R>(
         &self,
         query_name: &str,
         input: serde_json::Value,
-        storage_key: &str,
+        created: CreatedNode<'_>,
         validator: impl Fn(&I, &R) -> bool,
         storage_data_fn: impl Fn(&R) -> serde_json::Value,
         success_msg_fn: impl Fn(&R) -> String,
@@ -205,21 +347,203 @@ impl QueryValidator {
 
         if validator(&input_de, &db_result) {
             let storage_data = storage_data_fn(&db_result);
-            if let Err(e) = save_created_entity(storage_key, &storage_data) {
-                println!("Warning: Could not save {} data: {}", storage_key, e);
+            // checked on the node the query returned, before tracking may drop it as a repeat
+            if let Some((edge, parent_id)) = created.parent_edge {
+                let Some(id) = storage_data["id"].as_str().filter(|id| !id.is_empty()) else {
+                    return Ok(postcondition_failed(format!(
+                        "{} returned no id for the new node",
+                        query_name
+                    )));
+                };
+                if let Err(failure) = self.verify_single_source(edge, id, parent_id).await {
+                    return Ok(postcondition_failed(failure));
+                }
             }
-            Ok((true, success_msg_fn(&db_result)))
+            let note = self.track_created_node(&created, &storage_data).await;
+            Ok((true, format!("{}{}", success_msg_fn(&db_result), note)))
         } else {
             Ok((false, error_msg_fn(&input_de, &db_result)))
         }
     }
 
+    /// Records a node created by a check. When an earlier check already
+    /// created the same node and it still exists, the new copy is dropped so
+    /// repeated checks leave the graph unchanged. Returns a note for the
+    /// learner when that happens.
+    async fn track_created_node(
+        &self,
+        created: &CreatedNode<'_>,
+        data: &serde_json::Value,
+    ) -> String {
+        let storage_key = created.storage_key;
+        let name = data["name"].as_str().unwrap_or_default();
+        let new_id = data["id"].as_str().unwrap_or_default();
+        let same_name = |entity: &serde_json::Value| entity["name"].as_str() == Some(name);
+
+        let previous = find_created_entity(storage_key, same_name);
+        if let Some(entity_type) = EntityType::from_storage_key(storage_key)
+            && let Some(existing_id) = previous.as_ref().and_then(|entity| entity["id"].as_str())
+            && existing_id != new_id
+            && let Ok(true) = self.node_exists(entity_type.label(), existing_id).await
+        {
+            return match self
+                .drop_created_copy(created, entity_type.label(), new_id)
+                .await
+            {
+                Ok(()) => format!(
+                    "\n{} '{}' was already created by an earlier check, so this copy was removed again.",
+                    entity_type.label(),
+                    name
+                ),
+                Err(e) => format!(
+                    "\nWarning: could not remove the duplicate {} '{}': {}",
+                    entity_type.label(),
+                    name,
+                    e
+                ),
+            };
+        }
+
+        if let Err(e) = replace_created_entity(storage_key, data, same_name) {
+            return format!("\nWarning: could not save {} data: {}", storage_key, e);
+        }
+        // the earlier copy is gone, and with it the edges recorded for it
+        if let Some(previous_id) = previous.as_ref().and_then(|entity| entity["id"].as_str())
+            && previous_id != new_id
+            && let Err(e) = prune_created_entities(&HashSet::from([previous_id.to_string()]))
+        {
+            return format!("\nWarning: could not save {} data: {}", storage_key, e);
+        }
+
+        match created.parent_edge {
+            Some((edge, parent_id)) => self.track_created_edge(edge, parent_id, new_id).await,
+            None => String::new(),
+        }
+    }
+
+    /// Drops a node a check created again, along with the edge from its
+    /// parent.
+    async fn drop_created_copy(
+        &self,
+        created: &CreatedNode<'_>,
+        label: &str,
+        id: &str,
+    ) -> anyhow::Result<()> {
+        if let Some((edge, parent_id)) = created.parent_edge {
+            for edge_id in self.edge_ids(edge, parent_id, id).await? {
+                self.drop_edge(edge, &edge_id).await?;
+            }
+        }
+        self.drop_node(label, id).await
+    }
+
+    /// Records the `edge` edge a create query added from the new node's
+    /// parent, so it can be cleaned up with the node. Returns a warning for
+    /// the learner when that fails.
+    async fn track_created_edge(&self, edge: &str, from_id: &str, to_id: &str) -> String {
+        let edge_ids = match self.edge_ids(edge, from_id, to_id).await {
+            Ok(edge_ids) => edge_ids,
+            Err(e) => {
+                return format!(
+                    "\nWarning: could not look up the {} edge of '{}': {}",
+                    edge, to_id, e
+                );
+            }
+        };
+
+        let mut note = String::new();
+        for edge_id in edge_ids {
+            let record = json!({
+                "id": edge_id,
+                "label": edge,
+                "from_node": from_id,
+                "to_node": to_id
+            });
+            if let Err(e) = replace_created_entity("edges", &record, |entity| {
+                entity["id"].as_str() == Some(edge_id.as_str())
+            }) {
+                note = format!("\nWarning: could not save edges data: {}", e);
+            }
+        }
+        note
+    }
+
+    /// Ids of the `edge` edges from `from_id` to `to_id`.
+    async fn edge_ids(
+        &self,
+        edge: &str,
+        from_id: &str,
+        to_id: &str,
+    ) -> anyhow::Result<Vec<String>> {
+        Ok(self
+            .list_edges(edge)
+            .await?
+            .iter()
+            .filter(|record| {
+                record["from_node"].as_str() == Some(from_id)
+                    && record["to_node"].as_str() == Some(to_id)
+            })
+            .filter_map(|record| record["id"].as_str().map(str::to_string))
+            .collect())
+    }
+
+    /// Records a capital edge, dropping edges earlier checks created between
+    /// the same country and city. Returns a warning for the learner when the
+    /// record could not be saved.
+    async fn track_capital(&self, edge: &CapitalEdgeData) -> String {
+        let same_pair = |entity: &serde_json::Value| {
+            entity["country_id"].as_str() == Some(edge.from_node.as_str())
+                && entity["city_id"].as_str() == Some(edge.to_node.as_str())
+        };
+
+        if let Some(existing) = find_created_entity("capitals", same_pair)
+            && let Some(existing_id) = existing["id"].as_str()
+            && existing_id != edge.id
+        {
+            // the edge may already be gone with the instance it lived in
+            let _ = self.drop_edge(&edge.label, existing_id).await;
+        }
+
+        let record = json!({
+            "id": edge.id,
+            "label": edge.label,
+            "country_id": edge.from_node,
+            "city_id": edge.to_node
+        });
+        match replace_created_entity("capitals", &record, same_pair) {
+            Ok(()) => String::new(),
+            Err(e) => format!("\nWarning: could not save capitals data: {}", e),
+        }
+    }
+
+    /// Updates the recorded description of a city and regenerates its
+    /// embedding, which the nearest-neighbour check of searchDescriptions
+    /// ranks against.
+    fn track_description(&self, city_id: &str, description: &str) -> Result<(), String> {
+        let same_city = |entity: &serde_json::Value| entity["id"].as_str() == Some(city_id);
+        if let Some(mut city) = find_created_entity("cities", same_city) {
+            city["description"] = json!(description);
+            replace_created_entity("cities", &city, same_city)?;
+        }
+
+        // the new vector's id is not returned, and only the vector is ranked
+        replace_created_entity(
+            "embeddings",
+            &json!({
+                "city_id": city_id,
+                "vector": embed(description)
+            }),
+            |embedding| embedding["city_id"].as_str() == Some(city_id),
+        )
+    }
+
     async fn execute_get_query<I, R>(
         &self,
         query_name: &str,
         _entity_type: EntityType,
         input: serde_json::Value,
         validator: impl Fn(&R) -> bool,
+        expected: impl Fn(&serde_json::Value) -> Option<serde_json::Value>,
     ) -> anyhow::Result<(bool, String)>
     where
         I: DeserializeOwned + Serialize + Sync,
@@ -228,6 +552,7 @@ impl QueryValidator {
         let input_obj = serde_json::from_value::<serde_json::Value>(input)?;
         let input_obj = self.replace_placeholder_ids(input_obj)?;
 
+        let expected = expected(&input_obj);
         let input_de: I = serde_json::from_value(input_obj)?;
         let db_result: R = self.execute_query(query_name, &input_de).await?;
 
@@ -239,11 +564,11 @@ impl QueryValidator {
             );
             Ok((true, success_msg))
         } else {
-            let error_msg = format!(
-                "{} retrieval failed or returned empty data\nDatabase result:\n{}",
-                query_name,
-                serde_json::to_string_pretty(&db_result)?
-            );
+            let summary = format!("{} retrieval failed or returned empty data", query_name);
+            let error_msg = match expected {
+                Some(expected) => result_mismatch(summary, expected, &db_result),
+                None => empty_result(summary, &db_result),
+            };
             Ok((false, error_msg))
         }
     }
@@ -253,41 +578,59 @@ impl QueryValidator {
         query_name: &str,
         input: serde_json::Value,
     ) -> anyhow::Result<(bool, String)> {
+        let input = expand_embeddings(input);
         match query_name {
             "createContinent" => {
                 self.execute_create_query::<AddContinentInput, AddContinentResult>(
                     query_name,
                     input,
-                    "continents",
+                    CreatedNode {
+                        storage_key: "continents",
+                        parent_edge: None,
+                    },
                     |input, result| result.continent.name == input.name,
-                    |result| json!({
-                        "id": result.continent.id,
-                        "name": result.continent.name
-                    }),
-                    |result| format!(
-                        "Continent created successfully!\nDatabase result:\n{}",
-                        serde_json::to_string_pretty(result).unwrap_or_default()
-                    ),
-                    |input, result| format!(
-                        "Query executed but result doesn't match expected.\nDatabase returned:\n{}\nExpected name: '{}'",
-                        serde_json::to_string_pretty(result).unwrap_or_default(),
-                        input.name
-                    ),
-                ).await
+                    |result| {
+                        json!({
+                            "id": result.continent.id,
+                            "name": result.continent.name
+                        })
+                    },
+                    |result| {
+                        format!(
+                            "Continent created successfully!\nDatabase result:\n{}",
+                            serde_json::to_string_pretty(result).unwrap_or_default()
+                        )
+                    },
+                    |input, result| {
+                        result_mismatch(
+                            "Query executed but result doesn't match expected.",
+                            json!({"continent": {"name": input.name}}),
+                            result,
+                        )
+                    },
+                )
+                .await
             }
             "createCountry" => {
                 let input_obj = serde_json::from_value::<serde_json::Value>(input)?;
                 let input_obj = self.replace_placeholder_ids(input_obj)?;
+                let continent_id = input_obj["continent_id"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string();
 
                 self.execute_create_query::<AddCountryInput, AddCountryResult>(
                     query_name,
                     input_obj,
-                    "countries",
+                    CreatedNode {
+                        storage_key: "countries",
+                        parent_edge: Some(("Continent_to_Country", &continent_id)),
+                    },
                     |input, result| {
                         result.country.name == input.name
                             && result.country.currency == input.currency
                             && result.country.population == input.population
-                            && result.country.gdp == input.gdp
+                            && floats_match(result.country.gdp, input.gdp)
                     },
                     |result| {
                         let continent_id = get_latest_entity_id("continents").unwrap_or_default();
@@ -300,29 +643,43 @@ impl QueryValidator {
                             "continent_id": continent_id
                         })
                     },
-                    |result| format!(
-                        "Country created successfully!\nDatabase result:\n{}",
-                        serde_json::to_string_pretty(result).unwrap_or_default()
-                    ),
-                    |_, result| format!(
-                        "Country data mismatch\nDatabase result:\n{}",
-                        serde_json::to_string_pretty(result).unwrap_or_default()
-                    ),
-                ).await
+                    |result| {
+                        format!(
+                            "Country created successfully!\nDatabase result:\n{}",
+                            serde_json::to_string_pretty(result).unwrap_or_default()
+                        )
+                    },
+                    |input, result| {
+                        result_mismatch(
+                            "Country data mismatch",
+                            json!({"country": {
+                                "name": input.name,
+                                "currency": input.currency,
+                                "population": input.population,
+                                "gdp": input.gdp
+                            }}),
+                            result,
+                        )
+                    },
+                )
+                .await
             }
             "createCity" => {
                 let mut input_obj = serde_json::from_value::<serde_json::Value>(input)?;
-                
+
                 let city_name = input_obj["name"].as_str().unwrap_or("");
                 let city_to_country_mapping = Self::get_entity_mapping_for_lesson6();
-                
-                let country_id = if let Some(country_name) = city_to_country_mapping.get(city_name) {
+
+                let country_id = if let Some(country_name) = city_to_country_mapping.get(city_name)
+                {
                     self.get_country_id_by_name(country_name).ok_or_else(|| {
                         anyhow::anyhow!("Country '{}' not found for city '{}'. Please ensure countries are created first.", country_name, city_name)
                     })?
                 } else {
                     get_latest_entity_id("countries").ok_or_else(|| {
-                        anyhow::anyhow!("No country found. Please run lesson 6 first to create a country.")
+                        anyhow::anyhow!(
+                            "No country found. Please run lesson 6 first to create a country."
+                        )
                     })?
                 };
 
@@ -331,55 +688,73 @@ impl QueryValidator {
                 self.execute_create_query::<AddCityInput, AddCityResult>(
                     query_name,
                     input_obj,
-                    "cities",
+                    CreatedNode {
+                        storage_key: "cities",
+                        parent_edge: Some(("Country_to_City", &country_id)),
+                    },
+                    |input, result| {
+                        result.city.name == input.name
+                            && result.city.description == input.description
+                    },
+                    |result| {
+                        json!({
+                            "id": result.city.id,
+                            "name": result.city.name,
+                            "description": result.city.description,
+                            "country_id": country_id
+                        })
+                    },
+                    |result| {
+                        format!(
+                            "City created successfully!\nDatabase result:\n{}",
+                            serde_json::to_string_pretty(result).unwrap_or_default()
+                        )
+                    },
                     |input, result| {
-                        result.city.name == input.name && result.city.description == input.description
+                        result_mismatch(
+                            "City data mismatch",
+                            json!({"city": {"name": input.name, "description": input.description}}),
+                            result,
+                        )
                     },
-                    |result| json!({
-                        "id": result.city.id,
-                        "name": result.city.name,
-                        "description": result.city.description,
-                        "country_id": country_id
-                    }),
-                    |result| format!(
-                        "City created successfully!\nDatabase result:\n{}",
-                        serde_json::to_string_pretty(result).unwrap_or_default()
-                    ),
-                    |_, result| format!(
-                        "City data mismatch\nDatabase result:\n{}",
-                        serde_json::to_string_pretty(result).unwrap_or_default()
-                    ),
-                ).await
+                )
+                .await
             }
             "setCapital" => {
                 let input_obj = serde_json::from_value::<serde_json::Value>(input)?;
                 let input_obj = self.replace_placeholder_ids(input_obj)?;
-                
+
                 let country_id = input_obj["country_id"].as_str().unwrap().to_string();
                 let city_id = input_obj["city_id"].as_str().unwrap().to_string();
 
                 let input_de: AddCapitalInput = serde_json::from_value(input_obj)?;
-                let db_result: AddCapitalResult = self.execute_query("setCapital", &input_de).await?;
+                let db_result: AddCapitalResult =
+                    self.execute_query("setCapital", &input_de).await?;
 
                 let edge_matches = db_result.country_capital.from_node == country_id
                     && db_result.country_capital.to_node == city_id;
 
                 if edge_matches {
+                    if let Err(failure) = self
+                        .verify_edge("Country_to_Capital", &country_id, &city_id)
+                        .await
+                    {
+                        return Ok(postcondition_failed(failure));
+                    }
+                    let note = self.track_capital(&db_result.country_capital).await;
                     let success_msg = format!(
-                        "Capital relationship created successfully!\nDatabase result:\n{}\nCountry '{}' now has capital city '{}'.",
+                        "Capital relationship created successfully!\nDatabase result:\n{}\nCountry '{}' now has capital city '{}'.{}",
                         serde_json::to_string_pretty(&db_result)?,
                         country_id,
-                        city_id
+                        city_id,
+                        note
                     );
                     Ok((true, success_msg))
                 } else {
-                    let error_msg = format!(
-                        "Capital relationship mismatch\nExpected: from_node='{}', to_node='{}'\nGot: from_node='{}', to_node='{}'\nDatabase result:\n{}",
-                        country_id,
-                        city_id,
-                        db_result.country_capital.from_node,
-                        db_result.country_capital.to_node,
-                        serde_json::to_string_pretty(&db_result)?
+                    let error_msg = result_mismatch(
+                        "Capital relationship mismatch",
+                        json!({"country_capital": {"from_node": country_id, "to_node": city_id}}),
+                        &db_result,
                     );
                     Ok((false, error_msg))
                 }
@@ -387,16 +762,27 @@ impl QueryValidator {
             "embedDescription" => {
                 let input_obj = serde_json::from_value::<serde_json::Value>(input)?;
                 let input_obj = self.replace_placeholder_ids(input_obj)?;
-                
+
                 let city_id = input_obj["city_id"].as_str().unwrap().to_string();
 
                 let input_de: CreateDescEmbeddingInput = serde_json::from_value(input_obj)?;
-                let db_result: CreateDescEmbeddingResult = self.execute_query("embedDescription", &input_de).await?;
+                let db_result: CreateDescEmbeddingResult =
+                    self.execute_query("embedDescription", &input_de).await?;
 
                 let vector_matches = !db_result.embedding.data.is_empty()
-                    && db_result.embedding.data.len() == input_de.vector.len();
+                    && vectors_match(&db_result.embedding.data, &input_de.vector);
 
                 if vector_matches {
+                    // kept for the exact nearest-neighbour check of searchDescriptions
+                    let _ = replace_created_entity(
+                        "embeddings",
+                        &json!({
+                            "id": db_result.embedding.id,
+                            "city_id": city_id,
+                            "vector": input_de.vector
+                        }),
+                        |embedding| embedding["city_id"].as_str() == Some(&city_id),
+                    );
                     let success_msg = format!(
                         "Embedding created successfully!\nDatabase result:\n{}\nCity '{}' now has description embedding with {} dimensions.",
                         serde_json::to_string_pretty(&db_result)?,
@@ -405,11 +791,10 @@ impl QueryValidator {
                     );
                     Ok((true, success_msg))
                 } else {
-                    let error_msg = format!(
-                        "Embedding vector mismatch\nExpected vector length: {}\nGot vector length: {}\nDatabase result:\n{}",
-                        input_de.vector.len(),
-                        db_result.embedding.data.len(),
-                        serde_json::to_string_pretty(&db_result)?
+                    let error_msg = result_mismatch(
+                        "Embedding vector mismatch",
+                        json!({"embedding": {"data": input_de.vector}}),
+                        &db_result,
                     );
                     Ok((false, error_msg))
                 }
@@ -420,7 +805,9 @@ impl QueryValidator {
                     EntityType::Continent,
                     input,
                     |result| !result.continent.id.is_empty() && !result.continent.name.is_empty(),
-                ).await
+                    |input| Some(json!({"continent": {"id": input["continent_id"]}})),
+                )
+                .await
             }
             "getCountry" => {
                 self.execute_get_query::<GetCountryInput, GetCountryResult>(
@@ -428,7 +815,9 @@ impl QueryValidator {
                     EntityType::Country,
                     input,
                     |result| !result.country.id.is_empty() && !result.country.name.is_empty(),
-                ).await
+                    |input| Some(json!({"country": {"id": input["country_id"]}})),
+                )
+                .await
             }
             "getCity" => {
                 self.execute_get_query::<GetCityInput, GetCityResult>(
@@ -436,35 +825,40 @@ impl QueryValidator {
                     EntityType::City,
                     input,
                     |result| !result.city.id.is_empty() && !result.city.name.is_empty(),
-                ).await
+                    |input| Some(json!({"city": {"id": input["city_id"]}})),
+                )
+                .await
             }
             "getCapital" => {
                 let input_obj = serde_json::from_value::<serde_json::Value>(input)?;
                 let input_obj = self.replace_placeholder_ids(input_obj)?;
 
                 let input_de: GetCapitalInput = serde_json::from_value(input_obj)?;
-                
+
                 let db_result: GetCapitalResult = self.execute_query(query_name, &input_de).await?;
 
-                if !db_result.capital.is_empty() && 
-                   !db_result.capital[0].id.is_empty() && 
-                   !db_result.capital[0].name.is_empty() {
+                if !db_result.capital.is_empty()
+                    && !db_result.capital[0].id.is_empty()
+                    && !db_result.capital[0].name.is_empty()
+                {
                     let success_msg = format!(
                         "Capital retrieved successfully!\nDatabase result:\n{}",
                         serde_json::to_string_pretty(&db_result)?
                     );
                     Ok((true, success_msg))
                 } else {
-                    let error_msg = format!(
-                        "Capital retrieval failed or returned empty data\nDatabase result:\n{}",
-                        serde_json::to_string_pretty(&db_result)?
+                    let error_msg = empty_result(
+                        "Capital retrieval failed or returned empty data",
+                        &db_result,
                     );
                     Ok((false, error_msg))
                 }
             }
             "getAllContinents" => {
-                let db_result: GetAllContinentsResult = self.execute_query(query_name, &serde_json::json!({})).await?;
-                
+                let db_result: GetAllContinentsResult = self
+                    .execute_query(query_name, &serde_json::json!({}))
+                    .await?;
+
                 if !db_result.continents.is_empty() {
                     let success_msg = format!(
                         "All continents retrieved successfully!\nDatabase result:\n{}",
@@ -472,16 +866,15 @@ impl QueryValidator {
                     );
                     Ok((true, success_msg))
                 } else {
-                    let error_msg = format!(
-                        "No continents found or query failed\nDatabase result:\n{}",
-                        serde_json::to_string_pretty(&db_result)?
-                    );
+                    let error_msg = empty_result("No continents found or query failed", &db_result);
                     Ok((false, error_msg))
                 }
             }
             "getAllCountries" => {
-                let db_result: GetAllCountriesResult = self.execute_query(query_name, &serde_json::json!({})).await?;
-                
+                let db_result: GetAllCountriesResult = self
+                    .execute_query(query_name, &serde_json::json!({}))
+                    .await?;
+
                 if !db_result.countries.is_empty() {
                     let success_msg = format!(
                         "All countries retrieved successfully!\nDatabase result:\n{}",
@@ -489,16 +882,15 @@ impl QueryValidator {
                     );
                     Ok((true, success_msg))
                 } else {
-                    let error_msg = format!(
-                        "No countries found or query failed\nDatabase result:\n{}",
-                        serde_json::to_string_pretty(&db_result)?
-                    );
+                    let error_msg = empty_result("No countries found or query failed", &db_result);
                     Ok((false, error_msg))
                 }
             }
             "getAllCities" => {
-                let db_result: GetAllCitiesResult = self.execute_query(query_name, &serde_json::json!({})).await?;
-                
+                let db_result: GetAllCitiesResult = self
+                    .execute_query(query_name, &serde_json::json!({}))
+                    .await?;
+
                 if !db_result.cities.is_empty() {
                     let success_msg = format!(
                         "All cities retrieved successfully!\nDatabase result:\n{}",
@@ -506,32 +898,34 @@ impl QueryValidator {
                     );
                     Ok((true, success_msg))
                 } else {
-                    let error_msg = format!(
-                        "No cities found or query failed\nDatabase result:\n{}",
-                        serde_json::to_string_pretty(&db_result)?
-                    );
+                    let error_msg = empty_result("No cities found or query failed", &db_result);
                     Ok((false, error_msg))
                 }
             }
-            "getCountriesInContinent" => {
-                self.execute_get_query::<GetCountriesInContinentInput, GetCountriesInContinentResult>(
+            "getCountriesInContinent" => self
+                .execute_get_query::<GetCountriesInContinentInput, GetCountriesInContinentResult>(
                     query_name,
                     EntityType::Continent,
                     input,
                     |result| !result.countries.is_empty(),
-                ).await
-            }
+                    |_| None,
+                )
+                .await,
             "getCitiesInCountry" => {
                 self.execute_get_query::<GetCitiesInCountryInput, GetCitiesInCountryResult>(
                     query_name,
                     EntityType::Country,
                     input,
                     |result| !result.cities.is_empty(),
-                ).await
+                    |_| None,
+                )
+                .await
             }
             "getCountryNames" => {
-                let raw_response: serde_json::Value = self.execute_query(query_name, &serde_json::json!({})).await?;
-                
+                let raw_response: serde_json::Value = self
+                    .execute_query(query_name, &serde_json::json!({}))
+                    .await?;
+
                 let has_valid_data = if let Some(countries) = raw_response.get("countries") {
                     if let Some(countries_array) = countries.as_array() {
                         !countries_array.is_empty()
@@ -541,7 +935,7 @@ impl QueryValidator {
                 } else {
                     false
                 };
-                
+
                 if has_valid_data {
                     let success_msg = format!(
                         "Country names retrieved successfully!\nDatabase result:\n{}",
@@ -549,17 +943,16 @@ impl QueryValidator {
                     );
                     Ok((true, success_msg))
                 } else {
-                    let error_msg = format!(
-                        "No countries found or query failed\nDatabase result:\n{}",
-                        serde_json::to_string_pretty(&raw_response)?
-                    );
+                    let error_msg =
+                        empty_result("No countries found or query failed", &raw_response);
                     Ok((false, error_msg))
                 }
             }
             "getContinentByName" => {
                 let input_de: GetContinentByNameInput = serde_json::from_value(input)?;
-                let db_result: GetContinentByNameResult = self.execute_query(query_name, &input_de).await?;
-                
+                let db_result: GetContinentByNameResult =
+                    self.execute_query(query_name, &input_de).await?;
+
                 if !db_result.continent.is_empty() && !db_result.continent[0].id.is_empty() {
                     let success_msg = format!(
                         "Continent retrieved by name successfully!\nDatabase result:\n{}",
@@ -567,17 +960,19 @@ impl QueryValidator {
                     );
                     Ok((true, success_msg))
                 } else {
-                    let error_msg = format!(
-                        "No continent found with that name\nDatabase result:\n{}",
-                        serde_json::to_string_pretty(&db_result)?
+                    let error_msg = result_mismatch(
+                        "No continent found with that name",
+                        json!({"continent": [{"name": input_de.continent_name}]}),
+                        &db_result,
                     );
                     Ok((false, error_msg))
                 }
             }
             "getCountryByName" => {
                 let input_de: GetCountryByNameInput = serde_json::from_value(input)?;
-                let db_result: GetCountryByNameResult = self.execute_query(query_name, &input_de).await?;
-                
+                let db_result: GetCountryByNameResult =
+                    self.execute_query(query_name, &input_de).await?;
+
                 if !db_result.country.is_empty() && !db_result.country[0].id.is_empty() {
                     let success_msg = format!(
                         "Country retrieved by name successfully!\nDatabase result:\n{}",
@@ -585,17 +980,19 @@ impl QueryValidator {
                     );
                     Ok((true, success_msg))
                 } else {
-                    let error_msg = format!(
-                        "No country found with that name\nDatabase result:\n{}",
-                        serde_json::to_string_pretty(&db_result)?
+                    let error_msg = result_mismatch(
+                        "No country found with that name",
+                        json!({"country": [{"name": input_de.country_name}]}),
+                        &db_result,
                     );
                     Ok((false, error_msg))
                 }
             }
             "getCityByName" => {
                 let input_de: GetCityByNameInput = serde_json::from_value(input)?;
-                let db_result: GetCityByNameResult = self.execute_query(query_name, &input_de).await?;
-                
+                let db_result: GetCityByNameResult =
+                    self.execute_query(query_name, &input_de).await?;
+
                 if !db_result.city.is_empty() && !db_result.city[0].id.is_empty() {
                     let success_msg = format!(
                         "City retrieved by name successfully!\nDatabase result:\n{}",
@@ -603,17 +1000,19 @@ impl QueryValidator {
                     );
                     Ok((true, success_msg))
                 } else {
-                    let error_msg = format!(
-                        "No city found with that name\nDatabase result:\n{}",
-                        serde_json::to_string_pretty(&db_result)?
+                    let error_msg = result_mismatch(
+                        "No city found with that name",
+                        json!({"city": [{"name": input_de.city_name}]}),
+                        &db_result,
                     );
                     Ok((false, error_msg))
                 }
             }
             "getCountriesByCurrency" => {
                 let input_de: GetCountriesByCurrencyInput = serde_json::from_value(input)?;
-                let db_result: GetCountriesByCurrencyResult = self.execute_query(query_name, &input_de).await?;
-                
+                let db_result: GetCountriesByCurrencyResult =
+                    self.execute_query(query_name, &input_de).await?;
+
                 if !db_result.countries.is_empty() {
                     let success_msg = format!(
                         "Countries filtered by currency successfully!\nDatabase result:\n{}",
@@ -621,18 +1020,19 @@ impl QueryValidator {
                     );
                     Ok((true, success_msg))
                 } else {
-                    let error_msg = format!(
-                        "No countries found with currency '{}'\nDatabase result:\n{}",
-                        input_de.currency,
-                        serde_json::to_string_pretty(&db_result)?
+                    let error_msg = result_mismatch(
+                        format!("No countries found with currency '{}'", input_de.currency),
+                        json!({"countries": [{"currency": input_de.currency}]}),
+                        &db_result,
                     );
                     Ok((false, error_msg))
                 }
             }
             "getCountriesByPopulation" => {
                 let input_de: GetCountriesByPopulationInput = serde_json::from_value(input)?;
-                let db_result: GetCountriesByPopulationResult = self.execute_query(query_name, &input_de).await?;
-                
+                let db_result: GetCountriesByPopulationResult =
+                    self.execute_query(query_name, &input_de).await?;
+
                 if !db_result.countries.is_empty() {
                     let success_msg = format!(
                         "Countries filtered by population successfully!\nDatabase result:\n{}",
@@ -640,18 +1040,21 @@ impl QueryValidator {
                     );
                     Ok((true, success_msg))
                 } else {
-                    let error_msg = format!(
-                        "No countries found with population less than {}\nDatabase result:\n{}",
-                        input_de.max_population,
-                        serde_json::to_string_pretty(&db_result)?
+                    let error_msg = empty_result(
+                        format!(
+                            "No countries found with population less than {}",
+                            input_de.max_population
+                        ),
+                        &db_result,
                     );
                     Ok((false, error_msg))
                 }
             }
             "getCountriesByGdp" => {
                 let input_de: GetCountriesByGdpInput = serde_json::from_value(input)?;
-                let db_result: GetCountriesByGdpResult = self.execute_query(query_name, &input_de).await?;
-                
+                let db_result: GetCountriesByGdpResult =
+                    self.execute_query(query_name, &input_de).await?;
+
                 if !db_result.countries.is_empty() {
                     let success_msg = format!(
                         "Countries filtered by GDP successfully!\nDatabase result:\n{}",
@@ -659,18 +1062,21 @@ impl QueryValidator {
                     );
                     Ok((true, success_msg))
                 } else {
-                    let error_msg = format!(
-                        "No countries found with GDP greater than or equal to {}\nDatabase result:\n{}",
-                        input_de.min_gdp,
-                        serde_json::to_string_pretty(&db_result)?
+                    let error_msg = empty_result(
+                        format!(
+                            "No countries found with GDP greater than or equal to {}",
+                            input_de.min_gdp
+                        ),
+                        &db_result,
                     );
                     Ok((false, error_msg))
                 }
             }
             "getCountriesByPopGdp" => {
                 let input_de: GetCountriesByPopGdpInput = serde_json::from_value(input)?;
-                let db_result: GetCountriesByPopGdpResult = self.execute_query(query_name, &input_de).await?;
-                
+                let db_result: GetCountriesByPopGdpResult =
+                    self.execute_query(query_name, &input_de).await?;
+
                 if !db_result.countries.is_empty() {
                     let success_msg = format!(
                         "Countries filtered by population and GDP successfully!\nDatabase result:\n{}",
@@ -678,19 +1084,21 @@ impl QueryValidator {
                     );
                     Ok((true, success_msg))
                 } else {
-                    let error_msg = format!(
-                        "No countries found with population > {} and GDP <= {}\nDatabase result:\n{}",
-                        input_de.min_population,
-                        input_de.max_gdp,
-                        serde_json::to_string_pretty(&db_result)?
+                    let error_msg = empty_result(
+                        format!(
+                            "No countries found with population > {} and GDP <= {}",
+                            input_de.min_population, input_de.max_gdp
+                        ),
+                        &db_result,
                     );
                     Ok((false, error_msg))
                 }
             }
             "getCountriesByCurrPop" => {
                 let input_de: GetCountriesByCurrPopInput = serde_json::from_value(input)?;
-                let db_result: GetCountriesByCurrPopResult = self.execute_query(query_name, &input_de).await?;
-                
+                let db_result: GetCountriesByCurrPopResult =
+                    self.execute_query(query_name, &input_de).await?;
+
                 if !db_result.countries.is_empty() {
                     let success_msg = format!(
                         "Countries filtered by currency or population successfully!\nDatabase result:\n{}",
@@ -698,19 +1106,21 @@ impl QueryValidator {
                     );
                     Ok((true, success_msg))
                 } else {
-                    let error_msg = format!(
-                        "No countries found with currency '{}' or population <= {}\nDatabase result:\n{}",
-                        input_de.currency,
-                        input_de.max_population,
-                        serde_json::to_string_pretty(&db_result)?
+                    let error_msg = empty_result(
+                        format!(
+                            "No countries found with currency '{}' or population <= {}",
+                            input_de.currency, input_de.max_population
+                        ),
+                        &db_result,
                     );
                     Ok((false, error_msg))
                 }
             }
             "getContinentCities" => {
                 let input_de: GetContinentCitiesInput = serde_json::from_value(input)?;
-                let db_result: GetContinentCitiesResult = self.execute_query(query_name, &input_de).await?;
-                
+                let db_result: GetContinentCitiesResult =
+                    self.execute_query(query_name, &input_de).await?;
+
                 if !db_result.cities.is_empty() {
                     let success_msg = format!(
                         "Cities retrieved successfully from continent '{}'!\nDatabase result:\n{}",
@@ -719,29 +1129,36 @@ impl QueryValidator {
                     );
                     Ok((true, success_msg))
                 } else {
-                    let error_msg = format!(
-                        "No cities found in continent '{}'\nDatabase result:\n{}",
-                        input_de.continent_name,
-                        serde_json::to_string_pretty(&db_result)?
+                    let error_msg = empty_result(
+                        format!("No cities found in continent '{}'", input_de.continent_name),
+                        &db_result,
                     );
                     Ok((false, error_msg))
                 }
             }
             "countCapitals" => {
-                match self.client.query::<serde_json::Value, serde_json::Value>(query_name, &serde_json::json!({})).await {
+                match self
+                    .query_with_retry::<serde_json::Value, serde_json::Value>(
+                        &self.lesson_query_name(query_name),
+                        &serde_json::json!({}),
+                    )
+                    .await
+                {
                     Ok(raw_response) => {
                         let success_msg = format!(
                             "Capital count retrieved successfully!\nDatabase result:\n{}",
                             serde_json::to_string_pretty(&raw_response).unwrap_or_default()
                         );
                         Ok((true, success_msg))
-                    },
-                    Err(e) => Ok((false, format!("Query failed: {}", e)))
+                    }
+                    Err(e) => Ok((false, format!("Query failed: {}", e))),
                 }
             }
             "getCountriesWithCapitals" => {
-                let db_result: GetCountriesWithCapitalsResult = self.execute_query(query_name, &serde_json::json!({})).await?;
-                
+                let db_result: GetCountriesWithCapitalsResult = self
+                    .execute_query(query_name, &serde_json::json!({}))
+                    .await?;
+
                 if !db_result.countries.is_empty() {
                     let success_msg = format!(
                         "Countries with capitals retrieved successfully!\nDatabase result:\n{}",
@@ -749,42 +1166,102 @@ impl QueryValidator {
                     );
                     Ok((true, success_msg))
                 } else {
-                    let error_msg = format!(
-                        "No countries with capitals found or query failed\nDatabase result:\n{}",
-                        serde_json::to_string_pretty(&db_result)?
+                    let error_msg = empty_result(
+                        "No countries with capitals found or query failed",
+                        &db_result,
                     );
                     Ok((false, error_msg))
                 }
             }
             "getCountryByCityCnt" => {
                 let input_de: GetCountryByCityCntInput = serde_json::from_value(input)?;
-                
-                match self.client.query::<GetCountryByCityCntInput, serde_json::Value>(query_name, &input_de).await {
+
+                match self
+                    .query_with_retry::<GetCountryByCityCntInput, serde_json::Value>(
+                        &self.lesson_query_name(query_name),
+                        &input_de,
+                    )
+                    .await
+                {
                     Ok(raw_response) => {
                         let success_msg = format!(
                             "Countries filtered by city count successfully!\nDatabase result:\n{}",
                             serde_json::to_string_pretty(&raw_response).unwrap_or_default()
                         );
                         Ok((true, success_msg))
-                    },
-                    Err(e) => Ok((false, format!("Query failed: {}", e)))
+                    }
+                    Err(e) => Ok((false, format!("Query failed: {}", e))),
                 }
             }
             "searchDescriptions" => {
                 let input_de: SearchDescriptionsInput = serde_json::from_value(input)?;
-                let db_result: SearchDescriptionsResult = self.execute_query(query_name, &input_de).await?;
-                
-                if !db_result.cities.is_empty() {
+                let db_result: SearchDescriptionsResult =
+                    self.execute_query(query_name, &input_de).await?;
+
+                if db_result.cities.is_empty() {
+                    let error_msg = empty_result("No cities found for semantic search", &db_result);
+                    return Ok((false, error_msg));
+                }
+
+                let metric = config().search_metric;
+                let expected = nearest_cities(&input_de.vector, input_de.k as usize, metric);
+                if expected.is_empty() {
                     let success_msg = format!(
-                        "Semantic search completed successfully!\nDatabase result:\n{}\nFound {} semantically similar cities for the search vector.",
+                        "Semantic search completed successfully!\nDatabase result:\n{}\nFound {} semantically similar cities for the search vector.\nNo embeddings are recorded in instance.json, so the ranking was not checked. Press 's' to seed them.",
                         serde_json::to_string_pretty(&db_result)?,
                         db_result.cities.len()
                     );
+                    return Ok((true, success_msg));
+                }
+
+                let returned: Vec<String> = db_result
+                    .cities
+                    .iter()
+                    .map(|city| city.id.clone())
+                    .collect();
+                let comparison = compare_ranking(&expected, &returned);
+                let threshold = config().recall_threshold;
+
+                if comparison.recall >= threshold && comparison.order_matches {
+                    let success_msg = format!(
+                        "Semantic search completed successfully!\nDatabase result:\n{}\nFound {} of the {} nearest cities by {:?} distance, in the right order.",
+                        serde_json::to_string_pretty(&db_result)?,
+                        comparison.found,
+                        expected.len(),
+                        metric
+                    );
                     Ok((true, success_msg))
                 } else {
-                    let error_msg = format!(
-                        "No cities found for semantic search\nDatabase result:\n{}",
-                        serde_json::to_string_pretty(&db_result)?
+                    let problem = if comparison.recall < threshold {
+                        format!(
+                            "Recall {:.2} is below the threshold of {:.2}",
+                            comparison.recall, threshold
+                        )
+                    } else {
+                        "The cities are not ranked nearest first".to_string()
+                    };
+                    // the cities in the exact ranking, as returned where they were
+                    let ranked: Vec<serde_json::Value> = expected
+                        .iter()
+                        .map(|id| {
+                            db_result
+                                .cities
+                                .iter()
+                                .find(|city| &city.id == id)
+                                .and_then(|city| serde_json::to_value(city).ok())
+                                .unwrap_or_else(|| json!({"id": id}))
+                        })
+                        .collect();
+                    let error_msg = result_mismatch(
+                        format!(
+                            "{}\nExpected (exact {:?} ranking): {}\nGot: {}",
+                            problem,
+                            metric,
+                            expected.join(", "),
+                            returned.join(", ")
+                        ),
+                        json!({"cities": ranked}),
+                        &db_result,
                     );
                     Ok((false, error_msg))
                 }
@@ -792,16 +1269,27 @@ impl QueryValidator {
             "updateCurrency" => {
                 let input_obj = serde_json::from_value::<serde_json::Value>(input)?;
                 let input_obj = self.replace_placeholder_ids(input_obj)?;
-                
+
                 let country_id = input_obj["country_id"].as_str().unwrap().to_string();
 
                 let input_de: UpdateCurrencyInput = serde_json::from_value(input_obj)?;
-                let db_result: UpdateCurrencyResult = self.execute_query("updateCurrency", &input_de).await?;
+                let db_result: UpdateCurrencyResult =
+                    self.execute_query("updateCurrency", &input_de).await?;
 
-                let currency_matches = db_result.country.currency == input_de.currency 
+                let currency_matches = db_result.country.currency == input_de.currency
                     && db_result.country.id == country_id;
 
                 if currency_matches {
+                    if let Err(failure) = self
+                        .verify_properties(
+                            "Country",
+                            &country_id,
+                            &[("currency", json!(input_de.currency))],
+                        )
+                        .await
+                    {
+                        return Ok(postcondition_failed(failure));
+                    }
                     let success_msg = format!(
                         "Country currency updated successfully!\nDatabase result:\n{}\nCountry '{}' currency updated to '{}'.",
                         serde_json::to_string_pretty(&db_result)?,
@@ -810,11 +1298,10 @@ impl QueryValidator {
                     );
                     Ok((true, success_msg))
                 } else {
-                    let error_msg = format!(
-                        "Currency update mismatch\nExpected: currency='{}'\nGot: currency='{}'\nDatabase result:\n{}",
-                        input_de.currency,
-                        db_result.country.currency,
-                        serde_json::to_string_pretty(&db_result)?
+                    let error_msg = result_mismatch(
+                        "Currency update mismatch",
+                        json!({"country": {"id": country_id, "currency": input_de.currency}}),
+                        &db_result,
                     );
                     Ok((false, error_msg))
                 }
@@ -822,17 +1309,31 @@ impl QueryValidator {
             "updatePopGdp" => {
                 let input_obj = serde_json::from_value::<serde_json::Value>(input)?;
                 let input_obj = self.replace_placeholder_ids(input_obj)?;
-                
+
                 let country_id = input_obj["country_id"].as_str().unwrap().to_string();
 
                 let input_de: UpdatePopGdpInput = serde_json::from_value(input_obj)?;
-                let db_result: UpdatePopGdpResult = self.execute_query("updatePopGdp", &input_de).await?;
+                let db_result: UpdatePopGdpResult =
+                    self.execute_query("updatePopGdp", &input_de).await?;
 
-                let values_match = db_result.country.population == input_de.population 
-                    && db_result.country.gdp == input_de.gdp
+                let values_match = db_result.country.population == input_de.population
+                    && floats_match(db_result.country.gdp, input_de.gdp)
                     && db_result.country.id == country_id;
 
                 if values_match {
+                    if let Err(failure) = self
+                        .verify_properties(
+                            "Country",
+                            &country_id,
+                            &[
+                                ("population", json!(input_de.population)),
+                                ("gdp", json!(input_de.gdp)),
+                            ],
+                        )
+                        .await
+                    {
+                        return Ok(postcondition_failed(failure));
+                    }
                     let success_msg = format!(
                         "Country population and GDP updated successfully!\nDatabase result:\n{}\nCountry '{}' population updated to {} and GDP updated to {}.",
                         serde_json::to_string_pretty(&db_result)?,
@@ -842,13 +1343,10 @@ impl QueryValidator {
                     );
                     Ok((true, success_msg))
                 } else {
-                    let error_msg = format!(
-                        "Population/GDP update mismatch\nExpected: population={}, gdp={}\nGot: population={}, gdp={}\nDatabase result:\n{}",
-                        input_de.population,
-                        input_de.gdp,
-                        db_result.country.population,
-                        db_result.country.gdp,
-                        serde_json::to_string_pretty(&db_result)?
+                    let error_msg = result_mismatch(
+                        "Population/GDP update mismatch",
+                        json!({"country": {"id": country_id, "population": input_de.population, "gdp": input_de.gdp}}),
+                        &db_result,
                     );
                     Ok((false, error_msg))
                 }
@@ -856,15 +1354,22 @@ impl QueryValidator {
             "updateCapital" => {
                 let input_obj = serde_json::from_value::<serde_json::Value>(input)?;
                 let input_obj = self.replace_placeholder_ids(input_obj)?;
-                
+
                 let country_id = input_obj["country_id"].as_str().unwrap().to_string();
                 let city_id = input_obj["city_id"].as_str().unwrap().to_string();
 
                 let input_de: UpdateCapitalInput = serde_json::from_value(input_obj)?;
-                let db_result: UpdateCapitalResult = self.execute_query("updateCapital", &input_de).await?;
+                let db_result: UpdateCapitalResult =
+                    self.execute_query("updateCapital", &input_de).await?;
 
                 let capital_updated = db_result.city.id == city_id;
                 if capital_updated {
+                    if let Err(failure) = self
+                        .verify_single_edge("Country_to_Capital", &country_id, &city_id)
+                        .await
+                    {
+                        return Ok(postcondition_failed(failure));
+                    }
                     let success_msg = format!(
                         "Capital relationship updated successfully!\nDatabase result:\n{}\nCountry '{}' now has '{}' as its capital city.",
                         serde_json::to_string_pretty(&db_result)?,
@@ -873,11 +1378,10 @@ impl QueryValidator {
                     );
                     Ok((true, success_msg))
                 } else {
-                    let error_msg = format!(
-                        "Capital update mismatch\nExpected city ID: '{}'\nGot city ID: '{}'\nDatabase result:\n{}",
-                        city_id,
-                        db_result.city.id,
-                        serde_json::to_string_pretty(&db_result)?
+                    let error_msg = result_mismatch(
+                        "Capital update mismatch",
+                        json!({"city": {"id": city_id}}),
+                        &db_result,
                     );
                     Ok((false, error_msg))
                 }
@@ -885,29 +1389,44 @@ impl QueryValidator {
             "updateDescription" => {
                 let input_obj = serde_json::from_value::<serde_json::Value>(input)?;
                 let input_obj = self.replace_placeholder_ids(input_obj)?;
-                
+
                 let city_id = input_obj["city_id"].as_str().unwrap().to_string();
 
                 let input_de: UpdateDescriptionInput = serde_json::from_value(input_obj)?;
-                let db_result: UpdateDescriptionResult = self.execute_query("updateDescription", &input_de).await?;
+                let db_result: UpdateDescriptionResult =
+                    self.execute_query("updateDescription", &input_de).await?;
 
-                let description_updated = db_result.city.description == input_de.description 
+                let description_updated = db_result.city.description == input_de.description
                     && db_result.city.id == city_id;
 
                 if description_updated {
+                    if let Err(failure) = self
+                        .verify_properties(
+                            "City",
+                            &city_id,
+                            &[("description", json!(input_de.description))],
+                        )
+                        .await
+                    {
+                        return Ok(postcondition_failed(failure));
+                    }
+                    let note = match self.track_description(&city_id, &input_de.description) {
+                        Ok(()) => String::new(),
+                        Err(e) => format!("\nWarning: could not save the new description: {}", e),
+                    };
                     let success_msg = format!(
-                        "Description and embedding updated successfully!\nDatabase result:\n{}\nCity '{}' description updated to '{}' with new vector embedding.",
+                        "Description and embedding updated successfully!\nDatabase result:\n{}\nCity '{}' description updated to '{}' with new vector embedding.{}",
                         serde_json::to_string_pretty(&db_result)?,
                         city_id,
-                        input_de.description
+                        input_de.description,
+                        note
                     );
                     Ok((true, success_msg))
                 } else {
-                    let error_msg = format!(
-                        "Description update mismatch\nExpected description: '{}'\nGot description: '{}'\nDatabase result:\n{}",
-                        input_de.description,
-                        db_result.city.description,
-                        serde_json::to_string_pretty(&db_result)?
+                    let error_msg = result_mismatch(
+                        "Description update mismatch",
+                        json!({"city": {"id": city_id, "description": input_de.description}}),
+                        &db_result,
                     );
                     Ok((false, error_msg))
                 }
@@ -915,26 +1434,38 @@ impl QueryValidator {
             "deleteCity" => {
                 let input_obj = serde_json::from_value::<serde_json::Value>(input)?;
                 let input_obj = self.replace_placeholder_ids(input_obj)?;
-                
+
                 let city_id = input_obj["city_id"].as_str().unwrap().to_string();
 
                 let input_de: DeleteCityInput = serde_json::from_value(input_obj)?;
 
-                let db_result: DeleteCityResult = self.execute_query("deleteCity", &input_de).await?;
+                let db_result: DeleteCityResult =
+                    self.execute_query("deleteCity", &input_de).await?;
 
-                println!("db_result: {:?}", db_result);
                 if db_result.success == "success" {
+                    if let Err(failure) = self
+                        .verify_node_dropped(
+                            "City",
+                            &city_id,
+                            &["Country_to_City", "Country_to_Capital", "City_to_Embedding"],
+                        )
+                        .await
+                    {
+                        return Ok(postcondition_failed(failure));
+                    }
                     let success_msg = format!(
                         "City deleted successfully!\nDatabase result: \"{}\"\nCity '{}' has been removed from the graph.",
-                        db_result.success,
-                        city_id
+                        db_result.success, city_id
                     );
                     Ok((true, success_msg))
                 } else {
-                    let error_msg = format!(
-                        "City deletion failed: {}.\n\nTrying to delete city with ID: '{}'\n\nThis could be due to:\n1. HelixDB having issues with DELETE operations\n2. The city has dependencies (like being a capital) that prevent deletion\n3. Response format mismatch\n\nNote: The validation uses the latest created city from instance.json",
-                        db_result.success,
-                        city_id
+                    let error_msg = result_mismatch(
+                        format!(
+                            "City deletion failed: {}.\n\nTrying to delete city with ID: '{}'\n\nThis could be due to:\n1. HelixDB having issues with DELETE operations\n2. The city has dependencies (like being a capital) that prevent deletion\n3. Response format mismatch\n\nNote: The validation uses the latest created city from instance.json",
+                            db_result.success, city_id
+                        ),
+                        json!({"success": "success"}),
+                        &db_result,
                     );
                     Ok((false, error_msg))
                 }
@@ -942,25 +1473,34 @@ impl QueryValidator {
             "deleteCapital" => {
                 let input_obj = serde_json::from_value::<serde_json::Value>(input)?;
                 let input_obj = self.replace_placeholder_ids(input_obj)?;
-                
+
                 let country_id = input_obj["country_id"].as_str().unwrap().to_string();
 
                 let input_de: DeleteCapitalInput = serde_json::from_value(input_obj)?;
-                
-                let db_result: DeleteCapitalResult = self.execute_query("deleteCapital", &input_de).await?;
+
+                let db_result: DeleteCapitalResult =
+                    self.execute_query("deleteCapital", &input_de).await?;
 
                 if db_result.success == "success" {
+                    if let Err(failure) = self
+                        .verify_no_edges("Country_to_Capital", &country_id)
+                        .await
+                    {
+                        return Ok(postcondition_failed(failure));
+                    }
                     let success_msg = format!(
                         "Capital relationship deleted successfully!\nDatabase result: \"{}\"\nCountry '{}' no longer has a capital city relationship.",
-                        db_result.success,
-                        country_id
+                        db_result.success, country_id
                     );
                     Ok((true, success_msg))
                 } else {
-                    let error_msg = format!(
-                        "Capital deletion failed: {}.\n\nTrying to delete capital relationship for country ID: '{}'\n\nThis could be due to:\n1. HelixDB having issues with DELETE operations\n2. The country has dependencies (like having cities) that prevent deletion\n3. Response format mismatch\n\nNote: The validation uses the latest created country from instance.json",
-                        db_result.success,
-                        country_id
+                    let error_msg = result_mismatch(
+                        format!(
+                            "Capital deletion failed: {}.\n\nTrying to delete capital relationship for country ID: '{}'\n\nThis could be due to:\n1. HelixDB having issues with DELETE operations\n2. The country has dependencies (like having cities) that prevent deletion\n3. Response format mismatch\n\nNote: The validation uses the latest created country from instance.json",
+                            db_result.success, country_id
+                        ),
+                        json!({"success": "success"}),
+                        &db_result,
                     );
                     Ok((false, error_msg))
                 }
@@ -968,28 +1508,45 @@ impl QueryValidator {
             "deleteCountry" => {
                 let input_obj = serde_json::from_value::<serde_json::Value>(input)?;
                 let input_obj = self.replace_placeholder_ids(input_obj)?;
-                
+
                 let country_id = input_obj["country_id"].as_str().unwrap().to_string();
 
                 let input_de: DeleteCountryInput = serde_json::from_value(input_obj)?;
-                
-                let db_result: DeleteCountryResult = self.execute_query("deleteCountry", &input_de).await?;
+
+                let db_result: DeleteCountryResult =
+                    self.execute_query("deleteCountry", &input_de).await?;
                 if db_result.success == "success" {
+                    if let Err(failure) = self
+                        .verify_node_dropped(
+                            "Country",
+                            &country_id,
+                            &[
+                                "Continent_to_Country",
+                                "Country_to_City",
+                                "Country_to_Capital",
+                            ],
+                        )
+                        .await
+                    {
+                        return Ok(postcondition_failed(failure));
+                    }
                     let success_msg = format!(
                         "Country deleted successfully!\nDatabase result: \"{}\"\nCountry '{}' has been removed from the graph.",
-                        db_result.success,
-                            country_id
-                        );
-                        Ok((true, success_msg))
+                        db_result.success, country_id
+                    );
+                    Ok((true, success_msg))
                 } else {
-                    let error_msg = format!(
+                    let error_msg = result_mismatch(
+                        format!(
                             "Country deletion failed: {}.\n\nTrying to delete country ID: '{}'\n\nNote: HelixDB appears to have issues with DELETE operations",
-                            db_result.success,
-                            country_id
-                        );
-                        Ok((false, error_msg))
-                    }
+                            db_result.success, country_id
+                        ),
+                        json!({"success": "success"}),
+                        &db_result,
+                    );
+                    Ok((false, error_msg))
                 }
+            }
             _ => Ok((
                 false,
                 format!(
@@ -999,4 +1556,69 @@ impl QueryValidator {
             )),
         }
     }
-}
\ No newline at end of file
+}
+
+/// The query itself succeeded, but re-reading the graph showed it did not
+/// change the way the lesson asks for.
+fn postcondition_failed(failure: String) -> (bool, String) {
+    (
+        false,
+        format!(
+            "The query ran, but the graph did not change as expected.\nPost-condition failed: {}",
+            failure
+        ),
+    )
+}
+
+/// Explains a result the lesson does not accept: `summary`, then a diff of
+/// the result against the fields the lesson expects in it.
+pub(crate) fn result_mismatch(
+    summary: impl std::fmt::Display,
+    expected: serde_json::Value,
+    actual: &impl Serialize,
+) -> String {
+    let actual = serde_json::to_value(actual).unwrap_or_default();
+    let expected = overlay(&actual, expected);
+    format!(
+        "{}\n{}",
+        summary,
+        HelixFormatter::new().render_json_diff(&expected, &actual)
+    )
+}
+
+/// Explains a result that came back empty, where there are no expected
+/// items to diff it against: `summary`, then the result as returned.
+fn empty_result(summary: impl std::fmt::Display, actual: &impl Serialize) -> String {
+    format!(
+        "{}\nDatabase result:\n{}",
+        summary,
+        serde_json::to_string_pretty(actual).unwrap_or_default()
+    )
+}
+
+/// `actual` with the fields of `expected` laid over it, so fields the lesson
+/// does not check, such as generated ids, never show up as differences.
+fn overlay(actual: &serde_json::Value, expected: serde_json::Value) -> serde_json::Value {
+    match (actual, expected) {
+        (serde_json::Value::Object(actual_fields), serde_json::Value::Object(expected_fields)) => {
+            let mut merged = actual_fields.clone();
+            for (name, value) in expected_fields {
+                let field = actual_fields.get(&name).unwrap_or(&serde_json::Value::Null);
+                let value = overlay(field, value);
+                merged.insert(name, value);
+            }
+            serde_json::Value::Object(merged)
+        }
+        (serde_json::Value::Array(actual_items), serde_json::Value::Array(expected_items)) => {
+            expected_items
+                .into_iter()
+                .enumerate()
+                .map(|(index, item)| match actual_items.get(index) {
+                    Some(actual) => overlay(actual, item),
+                    None => item,
+                })
+                .collect()
+        }
+        (_, expected) => expected,
+    }
+}
@@ -0,0 +1,132 @@
+use std::path::{Path, PathBuf};
+
+use crate::validation::{ParsedSchema, SeedReference};
+
+/// Generated next to the learner's .hx files for the length of a deploy, so
+/// helixir can inspect and tidy up the data its checks create.
+pub const HELPER_FILE: &str = "helixir_helpers.hx";
+
+const HEADER: &str = "// Generated by helixir for checking lesson data. It is removed again once
+// the deploy finishes, so there is no need to edit it.";
+
+pub fn all_nodes_query(label: &str) -> String {
+    format!("helixir_nodes_{}", label)
+}
+
+pub fn node_query(label: &str) -> String {
+    format!("helixir_node_{}", label)
+}
+
+/// Nodes reached from a node over its outgoing `edge` edges.
+pub fn out_query(edge: &str) -> String {
+    format!("helixir_out_{}", edge)
+}
+
+/// Nodes an incoming `edge` edge comes from.
+pub fn in_query(edge: &str) -> String {
+    format!("helixir_in_{}", edge)
+}
+
+pub fn all_edges_query(label: &str) -> String {
+    format!("helixir_edges_{}", label)
+}
+
+pub fn drop_node_query(label: &str) -> String {
+    format!("helixir_drop_node_{}", label)
+}
+
+pub fn drop_edge_query(label: &str) -> String {
+    format!("helixir_drop_edge_{}", label)
+}
+
+pub fn drop_vectors_query(edge: &str) -> String {
+    format!("helixir_drop_vectors_{}", edge)
+}
+
+/// Helper queries for every node and edge type in the learner's schema, in a
+/// stable order so the output can be hashed. The seed queries are included
+/// when `seed` is given and fits the schema.
+pub fn helper_queries(schema: &ParsedSchema, seed: Option<&SeedReference>) -> String {
+    let mut nodes: Vec<&String> = schema.nodes.keys().collect();
+    nodes.sort();
+    let mut edges: Vec<&String> = schema.edges.keys().collect();
+    edges.sort();
+
+    let mut queries = vec![HEADER.to_string()];
+    for label in nodes {
+        queries.push(format!(
+            "QUERY {} () =>\n    nodes <- N<{}>\n    RETURN nodes",
+            all_nodes_query(label),
+            label
+        ));
+        queries.push(format!(
+            "QUERY {} (id: ID) =>\n    node <- N<{}>(id)\n    RETURN node",
+            node_query(label),
+            label
+        ));
+        queries.push(format!(
+            "QUERY {} (id: ID) =>\n    DROP N<{}>(id)\n    RETURN \"success\"",
+            drop_node_query(label),
+            label
+        ));
+    }
+    for label in edges {
+        queries.push(format!(
+            "QUERY {} () =>\n    edges <- E<{}>\n    RETURN edges",
+            all_edges_query(label),
+            label
+        ));
+        queries.push(format!(
+            "QUERY {} (id: ID) =>\n    DROP E<{}>(id)\n    RETURN \"success\"",
+            drop_edge_query(label),
+            label
+        ));
+        // vectors can only be reached through the edges pointing at them
+        let edge = &schema.edges[label];
+        if schema.vectors.contains_key(&edge.to_type) {
+            queries.push(format!(
+                "QUERY {} () =>\n    DROP N<{}>::Out<{}>\n    RETURN \"success\"",
+                drop_vectors_query(label),
+                edge.from_type,
+                label
+            ));
+        } else {
+            queries.push(format!(
+                "QUERY {} (id: ID) =>\n    targets <- N<{}>(id)::Out<{}>\n    RETURN targets",
+                out_query(label),
+                edge.from_type,
+                label
+            ));
+            queries.push(format!(
+                "QUERY {} (id: ID) =>\n    sources <- N<{}>(id)::In<{}>\n    RETURN sources",
+                in_query(label),
+                edge.to_type,
+                label
+            ));
+        }
+    }
+    if let Some(seed_queries) = seed.and_then(|seed| seed.queries_for(schema)) {
+        queries.push(seed_queries);
+    }
+
+    queries.join("\n\n") + "\n"
+}
+
+/// Keeps the helper file in the queries directory until dropped.
+pub struct HelperFile {
+    path: PathBuf,
+}
+
+impl HelperFile {
+    pub fn write(dir: &Path, content: &str) -> std::io::Result<Self> {
+        let path = dir.join(HELPER_FILE);
+        std::fs::write(&path, content)?;
+        Ok(Self { path })
+    }
+}
+
+impl Drop for HelperFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
@@ -1,11 +1,14 @@
-use std::{fs, process::Command};
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs,
+};
 
 use serde_json::json;
 
-pub fn load_instance_data() -> serde_json::Value {
-    let instance_file = "instance.json";
+use crate::project::project;
 
-    if let Ok(content) = fs::read_to_string(instance_file) {
+pub fn load_instance_data() -> serde_json::Value {
+    if let Ok(content) = fs::read_to_string(project().progress_file()) {
         serde_json::from_str(&content).unwrap_or_else(|_| create_default_instance_data())
     } else {
         create_default_instance_data()
@@ -19,36 +22,105 @@ pub fn create_default_instance_data() -> serde_json::Value {
         "created_entities": {
             "continents": [],
             "countries": [],
-            "cities": []
+            "cities": [],
+            "capitals": [],
+            "embeddings": [],
+            "edges": []
         }
     })
 }
 
 pub fn save_instance_data(data: &serde_json::Value) -> Result<(), String> {
-    let instance_file = "instance.json";
     let content = serde_json::to_string_pretty(data)
         .map_err(|e| format!("Failed to serialize instance data: {}", e))?;
 
-    fs::write(instance_file, content)
+    fs::write(project().progress_file(), content)
         .map_err(|e| format!("Failed to write instance file: {}", e))?;
 
     Ok(())
 }
 
-pub fn save_created_entity(
+/// Records an entity the checker created, replacing any earlier record for
+/// which `same` returns true.
+pub fn replace_created_entity(
     entity_type: &str,
     entity_data: &serde_json::Value,
+    same: impl Fn(&serde_json::Value) -> bool,
 ) -> Result<(), String> {
     let mut instance_data = load_instance_data();
-    if let Some(entities_array) = instance_data["created_entities"][entity_type].as_array_mut() {
+    let entities = &mut instance_data["created_entities"][entity_type];
+    if !entities.is_array() {
+        *entities = json!([]);
+    }
+
+    if let Some(entities_array) = entities.as_array_mut() {
+        entities_array.retain(|entity| !same(entity));
         entities_array.push(entity_data.clone());
-    } else {
-        return Err(format!("Invalid entity type: {}", entity_type));
     }
 
     save_instance_data(&instance_data)
 }
 
+pub fn find_created_entity(
+    entity_type: &str,
+    matches: impl Fn(&serde_json::Value) -> bool,
+) -> Option<serde_json::Value> {
+    let instance_data = load_instance_data();
+    instance_data["created_entities"][entity_type]
+        .as_array()?
+        .iter()
+        .find(|entity| matches(entity))
+        .cloned()
+}
+
+/// Ids of every entity the checker has recorded, across all entity types.
+pub fn created_entity_ids() -> HashSet<String> {
+    let instance_data = load_instance_data();
+    let Some(created) = instance_data["created_entities"].as_object() else {
+        return HashSet::new();
+    };
+
+    created
+        .values()
+        .filter_map(|entities| entities.as_array())
+        .flatten()
+        .filter_map(|entity| entity["id"].as_str().map(str::to_string))
+        .collect()
+}
+
+/// Drops records of removed entities, of the edges that touched them, and
+/// repeated records of the same entity.
+pub fn prune_created_entities(removed_ids: &HashSet<String>) -> Result<(), String> {
+    let mut instance_data = load_instance_data();
+    if let Some(created) = instance_data["created_entities"].as_object_mut() {
+        for entities in created.values_mut().filter_map(|v| v.as_array_mut()) {
+            let mut seen = HashSet::new();
+            entities.retain(|entity| {
+                let touches_removed = ["from_node", "to_node"].iter().any(|end| {
+                    entity[end]
+                        .as_str()
+                        .is_some_and(|id| removed_ids.contains(id))
+                });
+                match entity["id"].as_str() {
+                    Some(id) => {
+                        !removed_ids.contains(id) && !touches_removed && seen.insert(id.to_string())
+                    }
+                    None => true,
+                }
+            });
+        }
+    }
+
+    save_instance_data(&instance_data)
+}
+
+/// Forgets every recorded entity, once the instance they lived in was wiped.
+pub fn reset_created_entities() -> Result<(), String> {
+    let mut instance_data = load_instance_data();
+    instance_data["created_entities"] = create_default_instance_data()["created_entities"].take();
+    save_instance_data(&instance_data)
+}
+
 pub fn get_latest_entity_id(entity_type: &str) -> Option<String> {
     let instance_data = load_instance_data();
 
@@ -103,77 +175,23 @@ pub fn is_lesson_completed(lesson_id: usize) -> bool {
     get_completed_lessons().contains(&lesson_id)
 }
 
-pub fn redeploy_instance() -> bool {
-    println!("Building and deploying instance with 'helix build dev'...");
-
-    // Run helix build dev
-    let build_output = Command::new("helix")
-        .args(["build", "dev"])
-        .output();
-
-    match build_output {
-        Ok(result) => {
-            let stdout_str = String::from_utf8_lossy(&result.stdout);
-            let stderr_str = String::from_utf8_lossy(&result.stderr);
-
-            if !stdout_str.is_empty() {
-                println!("Build output: {}", stdout_str);
-            }
-            if !stderr_str.is_empty() {
-                println!("Build errors: {}", stderr_str);
-            }
-
-            if stdout_str.contains("Parse error") || stderr_str.contains("Parse error") {
-                println!("Build failed due to parse errors in queries.hx or schema.hx");
-                return false;
-            }
-
-            if stdout_str.contains("Error compiling") || stderr_str.contains("Error compiling") {
-                println!("Build failed due to compilation errors");
-                return false;
-            }
-
-            if !result.status.success() {
-                println!("Build failed with exit code: {:?}", result.status.code());
-                return false;
-            }
-
-            println!("Build successful! Starting instance with 'helix push dev'...");
-        }
-        Err(e) => {
-            println!("Error running helix build command: {}", e);
-            return false;
-        }
-    }
+/// Records what was last deployed so unchanged sources can skip the rebuild.
+pub fn save_deployment_state(
+    source_hashes: &BTreeMap<String, String>,
+    status: &str,
+) -> Result<(), String> {
+    let mut instance_data = load_instance_data();
+    instance_data["deployment"] = json!({
+        "source_hashes": source_hashes,
+        "status": status,
+    });
+    save_instance_data(&instance_data)
+}
 
-    // Run helix push dev
-    let push_output = Command::new("helix")
-        .args(["push", "dev"])
-        .output();
-
-    match push_output {
-        Ok(result) => {
-            let stdout_str = String::from_utf8_lossy(&result.stdout);
-            let stderr_str = String::from_utf8_lossy(&result.stderr);
-
-            if !stdout_str.is_empty() {
-                println!("Push output: {}", stdout_str);
-            }
-            if !stderr_str.is_empty() {
-                println!("Push errors: {}", stderr_str);
-            }
-
-            if result.status.success() {
-                println!("Instance deployed successfully!");
-                true
-            } else {
-                println!("Failed to push instance");
-                false
-            }
-        }
-        Err(e) => {
-            println!("Error running helix push command: {}", e);
-            false
-        }
-    }
+pub fn load_deployment_state() -> Option<(BTreeMap<String, String>, String)> {
+    let instance_data = load_instance_data();
+    let deployment = instance_data.get("deployment")?;
+    let source_hashes = serde_json::from_value(deployment["source_hashes"].clone()).ok()?;
+    let status = deployment["status"].as_str()?.to_string();
+    Some((source_hashes, status))
 }
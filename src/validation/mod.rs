@@ -1,10 +1,30 @@
+pub mod cases;
+pub mod cleanup;
+pub mod compare;
+pub mod deploy;
+pub mod diagnostics;
+pub mod embed;
 pub mod executor;
+pub mod helpers;
 pub mod instance;
+pub mod postconditions;
+pub mod probes;
 pub mod query;
 pub mod schema;
+pub mod search;
+pub mod seed;
+pub mod shape;
 pub mod types;
 pub mod utils;
 
+pub use compare::*;
+pub use deploy::*;
+pub use diagnostics::*;
+pub use embed::*;
+pub use helpers::*;
 pub use instance::*;
+pub use search::*;
+pub use seed::*;
+pub use shape::*;
 pub use types::*;
 pub use utils::*;
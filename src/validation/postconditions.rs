@@ -0,0 +1,182 @@
+use std::collections::HashSet;
+
+use crate::validation::{QueryValidator, prune_created_entities, values_match};
+
+impl QueryValidator {
+    /// Re-reads node `id` and checks that each property has the value the
+    /// update set.
+    pub(super) async fn verify_properties(
+        &self,
+        label: &str,
+        id: &str,
+        expected: &[(&str, serde_json::Value)],
+    ) -> Result<(), String> {
+        let node = self
+            .find_node(label, id)
+            .await?
+            .ok_or_else(|| format!("{} '{}' no longer exists after the update", label, id))?;
+
+        for (property, value) in expected {
+            if !values_match(&node[*property], value) {
+                return Err(format!(
+                    "re-reading {} '{}' shows {}={}, expected {}",
+                    label, id, property, node[*property], value
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that node `id` is gone along with its edges of `edge_labels`,
+    /// then forgets it in instance.json.
+    pub(super) async fn verify_node_dropped(
+        &self,
+        label: &str,
+        id: &str,
+        edge_labels: &[&str],
+    ) -> Result<(), String> {
+        if self.find_node(label, id).await?.is_some() {
+            return Err(format!("{} '{}' is still in the graph", label, id));
+        }
+        for edge_label in edge_labels {
+            self.verify_no_edges(edge_label, id).await?;
+        }
+
+        prune_created_entities(&HashSet::from([id.to_string()]))
+    }
+
+    /// Checks that no edge of type `label` starts or ends at `node_id`.
+    pub(super) async fn verify_no_edges(&self, label: &str, node_id: &str) -> Result<(), String> {
+        let remaining: Vec<String> = self
+            .edges_touching(label, node_id)
+            .await?
+            .iter()
+            .map(|edge| edge["id"].as_str().unwrap_or("?").to_string())
+            .collect();
+
+        if remaining.is_empty() {
+            return Ok(());
+        }
+        Err(format!(
+            "{} {} edge{} still connected to '{}' ({})",
+            remaining.len(),
+            label,
+            if remaining.len() == 1 { " is" } else { "s are" },
+            node_id,
+            remaining.join(", ")
+        ))
+    }
+
+    /// Checks that `from_id` has an outgoing `label` edge to `to_id`.
+    pub(super) async fn verify_edge(
+        &self,
+        label: &str,
+        from_id: &str,
+        to_id: &str,
+    ) -> Result<(), String> {
+        let targets = self.edge_targets(label, from_id).await?;
+        if targets.iter().any(|target| target == to_id) {
+            return Ok(());
+        }
+        Err(format!(
+            "no {} edge from '{}' to '{}'{}",
+            label,
+            from_id,
+            to_id,
+            if targets.is_empty() {
+                String::new()
+            } else {
+                format!(" (it points at {})", targets.join(", "))
+            }
+        ))
+    }
+
+    /// Checks that `from_id` has exactly one outgoing `label` edge, and that
+    /// it points at `to_id`.
+    pub(super) async fn verify_single_edge(
+        &self,
+        label: &str,
+        from_id: &str,
+        to_id: &str,
+    ) -> Result<(), String> {
+        let targets = self.edge_targets(label, from_id).await?;
+
+        match targets.as_slice() {
+            [target] if target == to_id => Ok(()),
+            [] => Err(format!("'{}' has no {} edge", from_id, label)),
+            [target] => Err(format!(
+                "the {} edge of '{}' points at '{}', expected '{}'",
+                label, from_id, target, to_id
+            )),
+            _ => Err(format!(
+                "'{}' has {} {} edges (to {}), expected only the one to '{}'",
+                from_id,
+                targets.len(),
+                label,
+                targets.join(", "),
+                to_id
+            )),
+        }
+    }
+
+    /// Checks that `to_id` has exactly one incoming `label` edge, and that
+    /// it comes from `from_id`.
+    pub(super) async fn verify_single_source(
+        &self,
+        label: &str,
+        to_id: &str,
+        from_id: &str,
+    ) -> Result<(), String> {
+        let sources = self
+            .in_neighbours(label, to_id)
+            .await
+            .map_err(|e| format!("could not read the {} edges into '{}': {}", label, to_id, e))?;
+
+        match sources.as_slice() {
+            [source] if source == from_id => Ok(()),
+            [] => Err(format!("no {} edge leads to '{}'", label, to_id)),
+            [source] => Err(format!(
+                "the {} edge into '{}' comes from '{}', expected '{}'",
+                label, to_id, source, from_id
+            )),
+            _ => Err(format!(
+                "'{}' has {} incoming {} edges (from {}), expected only the one from '{}'",
+                to_id,
+                sources.len(),
+                label,
+                sources.join(", "),
+                from_id
+            )),
+        }
+    }
+
+    async fn edge_targets(&self, label: &str, from_id: &str) -> Result<Vec<String>, String> {
+        self.out_neighbours(label, from_id)
+            .await
+            .map_err(|e| format!("could not read the {} edges of '{}': {}", label, from_id, e))
+    }
+
+    async fn find_node(&self, label: &str, id: &str) -> Result<Option<serde_json::Value>, String> {
+        self.node_by_id(label, id)
+            .await
+            .map_err(|e| format!("could not re-read {} '{}': {}", label, id, e))
+    }
+
+    async fn edges_touching(
+        &self,
+        label: &str,
+        node_id: &str,
+    ) -> Result<Vec<serde_json::Value>, String> {
+        let edges = self
+            .list_edges(label)
+            .await
+            .map_err(|e| format!("could not re-read {} edges: {}", label, e))?;
+        Ok(edges
+            .into_iter()
+            .filter(|edge| {
+                edge["from_node"].as_str() == Some(node_id)
+                    || edge["to_node"].as_str() == Some(node_id)
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,60 @@
+use helix_rs::HelixError;
+use serde_json::json;
+
+use crate::validation::{QueryValidator, in_query, node_query, out_query};
+
+impl QueryValidator {
+    /// Node `id` of type `label`, or `None` when the instance has no such
+    /// node.
+    pub async fn node_by_id(
+        &self,
+        label: &str,
+        id: &str,
+    ) -> anyhow::Result<Option<serde_json::Value>> {
+        match self
+            .query_with_retry::<_, serde_json::Value>(&node_query(label), &json!({ "id": id }))
+            .await
+        {
+            Ok(response) => Ok(first_node(response)),
+            // looking up a missing id is an error on the server side
+            Err(HelixError::RemoteError { .. }) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Ids of the nodes `id` points at over its outgoing `edge` edges.
+    pub async fn out_neighbours(&self, edge: &str, id: &str) -> anyhow::Result<Vec<String>> {
+        self.neighbours(&out_query(edge), id).await
+    }
+
+    /// Ids of the nodes with an `edge` edge pointing at `id`.
+    pub async fn in_neighbours(&self, edge: &str, id: &str) -> anyhow::Result<Vec<String>> {
+        self.neighbours(&in_query(edge), id).await
+    }
+
+    async fn neighbours(&self, query: &str, id: &str) -> anyhow::Result<Vec<String>> {
+        let response: serde_json::Value =
+            self.query_with_retry(query, &json!({ "id": id })).await?;
+
+        Ok(response
+            .as_object()
+            .and_then(|fields| fields.values().find_map(|value| value.as_array()))
+            .into_iter()
+            .flatten()
+            .filter_map(|node| node["id"].as_str().map(str::to_string))
+            .collect())
+    }
+}
+
+/// A single node comes back as an object, or as a one-element array.
+fn first_node(response: serde_json::Value) -> Option<serde_json::Value> {
+    let serde_json::Value::Object(fields) = response else {
+        return None;
+    };
+
+    fields.into_iter().find_map(|(_, value)| match value {
+        serde_json::Value::Object(_) => Some(value),
+        serde_json::Value::Array(items) => items.into_iter().find(|item| item.is_object()),
+        _ => None,
+    })
+}
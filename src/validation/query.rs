@@ -1,15 +1,30 @@
 use std::{
     collections::{HashMap, HashSet},
-    fs,
+    path::Path,
 };
 
-use crate::validation::{ParsedQueries, ParsedQuery, QueryValidationResult};
+use crate::validation::{
+    ParsedQueries, ParsedQuery, QueryMismatch, QueryValidationResult, check_duplicates,
+    read_hx_sources,
+};
 
 impl ParsedQueries {
-    pub fn from_file(file_path: &str) -> Result<Self, String> {
-        let content = fs::read_to_string(file_path)
-            .map_err(|e| format!("Failed to read file '{}': {}", file_path, e))?;
-        Self::parse(&content)
+    /// Merges the QUERY definitions of every .hx file in `dir`.
+    pub fn from_dir(dir: &Path) -> Result<Self, String> {
+        let mut queries = HashMap::new();
+        let mut definitions = Vec::new();
+
+        for (file, content) in read_hx_sources(dir)? {
+            definitions.extend(
+                definition_lines(&content)
+                    .into_iter()
+                    .map(|(name, line)| (name, format!("{}:{}", file, line))),
+            );
+            queries.extend(Self::parse(&content)?.queries);
+        }
+
+        check_duplicates(definitions)?;
+        Ok(ParsedQueries { queries })
     }
 
     pub fn from_string(content: &str) -> Result<Self, String> {
@@ -29,42 +44,42 @@ impl ParsedQueries {
                 continue;
             }
 
-            if line.starts_with("QUERY ") {
-                if let Some(arrow_pos) = line.find(" =>") {
-                    let query_def = &line[6..arrow_pos];
+            if line.starts_with("QUERY ")
+                && let Some(arrow_pos) = line.find(" =>")
+            {
+                let query_def = &line[6..arrow_pos];
 
-                    if let Some(paren_pos) = query_def.find('(') {
-                        let query_name = query_def[..paren_pos].trim().to_string();
-                        let params_end = query_def.rfind(')').unwrap_or(query_def.len());
-                        let parameters = query_def[paren_pos + 1..params_end].trim().to_string();
+                if let Some(paren_pos) = query_def.find('(') {
+                    let query_name = query_def[..paren_pos].trim().to_string();
+                    let params_end = query_def.rfind(')').unwrap_or(query_def.len());
+                    let parameters = query_def[paren_pos + 1..params_end].trim().to_string();
 
-                        let mut body_lines = Vec::new();
-                        i += 1;
+                    let mut body_lines = Vec::new();
+                    i += 1;
 
-                        while i < lines.len() {
-                            let body_line = lines[i].trim();
-                            if body_line.is_empty() || body_line.starts_with("//") {
-                                i += 1;
-                                continue;
-                            }
-                            if body_line.starts_with("QUERY ") {
-                                i -= 1;
-                                break;
-                            }
-                            body_lines.push(body_line);
+                    while i < lines.len() {
+                        let body_line = lines[i].trim();
+                        if body_line.is_empty() || body_line.starts_with("//") {
                             i += 1;
+                            continue;
                         }
-
-                        let body = body_lines.join("\n");
-                        queries.insert(
-                            query_name.clone(),
-                            ParsedQuery {
-                                name: query_name,
-                                parameters,
-                                body,
-                            },
-                        );
+                        if body_line.starts_with("QUERY ") || is_schema_definition(body_line) {
+                            i -= 1;
+                            break;
+                        }
+                        body_lines.push(body_line);
+                        i += 1;
                     }
+
+                    let body = body_lines.join("\n");
+                    queries.insert(
+                        query_name.clone(),
+                        ParsedQuery {
+                            name: query_name,
+                            parameters,
+                            body,
+                        },
+                    );
                 }
             }
             i += 1;
@@ -73,11 +88,22 @@ impl ParsedQueries {
         Ok(ParsedQueries { queries })
     }
 
-    pub fn validate_against(&self, expected: &ParsedQueries) -> QueryValidationResult {
+    /// Compares against the expected queries named in `scope`, or against all
+    /// of them when `scope` is `None`.
+    pub fn validate_against(
+        &self,
+        expected: &ParsedQueries,
+        scope: Option<&[String]>,
+    ) -> QueryValidationResult {
         let mut query_errors = HashMap::new();
 
         let user_queries: HashSet<String> = self.queries.keys().cloned().collect();
-        let expected_queries: HashSet<String> = expected.queries.keys().cloned().collect();
+        let expected_queries: HashSet<String> = expected
+            .queries
+            .keys()
+            .filter(|name| scope.is_none_or(|names| names.contains(name)))
+            .cloned()
+            .collect();
 
         let missing_queries: Vec<String> = expected_queries
             .difference(&user_queries)
@@ -106,7 +132,14 @@ impl ParsedQueries {
             }
 
             if !errors.is_empty() {
-                query_errors.insert(query_name.clone(), errors.join(". "));
+                query_errors.insert(
+                    query_name.clone(),
+                    QueryMismatch {
+                        errors,
+                        expected_source: expected_query.source(),
+                        actual_source: user_query.source(),
+                    },
+                );
             }
         }
 
@@ -121,6 +154,38 @@ impl ParsedQueries {
     }
 }
 
+impl ParsedQuery {
+    /// The query as written, with the body indented under its header.
+    pub fn source(&self) -> String {
+        let mut source = format!("QUERY {}({}) =>", self.name, self.parameters);
+        for line in self.body.lines() {
+            source.push_str("\n    ");
+            source.push_str(line);
+        }
+        source
+    }
+}
+
+/// Each QUERY header, as `QUERY name`, with its 1-based line number.
+fn definition_lines(content: &str) -> Vec<(String, usize)> {
+    content
+        .lines()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            let after_keyword = line.trim().strip_prefix("QUERY ")?;
+            let name = after_keyword[..after_keyword.find('(')?].trim();
+            Some((format!("QUERY {}", name), index + 1))
+        })
+        .collect()
+}
+
+/// Schema definitions can share a file with queries and end the query before them.
+fn is_schema_definition(line: &str) -> bool {
+    ["N::", "E::", "V::"]
+        .iter()
+        .any(|prefix| line.starts_with(prefix))
+}
+
 fn normalize_query_body(body: &str) -> String {
     body.lines()
         .map(|line| line.trim())
@@ -1,16 +1,37 @@
 use crate::validation::{
     EdgeErrors, EdgeInfo, ParsedSchema, Property, PropertyErrors, ValidationResult,
+    check_duplicates, read_hx_sources,
 };
 use std::{
     collections::{HashMap, HashSet},
-    fs,
+    path::Path,
 };
 
 impl ParsedSchema {
-    pub fn from_file(file_path: &str) -> Result<Self, String> {
-        let content = fs::read_to_string(file_path)
-            .map_err(|e| format!("Failed to read file '{}': {}", file_path, e))?;
-        Self::parse(&content)
+    /// Merges the N::, E:: and V:: definitions of every .hx file in `dir`.
+    pub fn from_dir(dir: &Path) -> Result<Self, String> {
+        let mut schema = ParsedSchema {
+            nodes: HashMap::new(),
+            edges: HashMap::new(),
+            vectors: HashMap::new(),
+        };
+        let mut definitions = Vec::new();
+
+        for (file, content) in read_hx_sources(dir)? {
+            definitions.extend(
+                definition_lines(&content)
+                    .into_iter()
+                    .map(|(name, line)| (name, format!("{}:{}", file, line))),
+            );
+
+            let parsed = Self::parse(&content)?;
+            schema.nodes.extend(parsed.nodes);
+            schema.edges.extend(parsed.edges);
+            schema.vectors.extend(parsed.vectors);
+        }
+
+        check_duplicates(definitions)?;
+        Ok(schema)
     }
 
     pub fn from_string(content: &str) -> Result<Self, String> {
@@ -335,6 +356,20 @@ impl ParsedSchema {
     }
 }
 
+/// Each schema definition header, e.g. `N::Country`, with its 1-based line number.
+fn definition_lines(content: &str) -> Vec<(String, usize)> {
+    content
+        .lines()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            let line = line.trim();
+            detect_schema_type(line)?;
+            let header = &line[..line.find('{')?];
+            Some((header.trim().to_string(), index + 1))
+        })
+        .collect()
+}
+
 fn detect_schema_type(line: &str) -> Option<(&str, &str)> {
     if let Some(after) = line.strip_prefix("N::") {
         Some(("node", after))
@@ -0,0 +1,102 @@
+use std::collections::HashSet;
+
+use serde::Deserialize;
+
+use crate::validation::load_instance_data;
+
+/// How the semantic search lesson ranks vectors when working out the exact
+/// nearest neighbours.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchMetric {
+    #[default]
+    Cosine,
+    Euclidean,
+}
+
+impl SearchMetric {
+    /// Smaller is nearer. `None` when the vectors can't be compared.
+    pub fn distance(self, a: &[f64], b: &[f64]) -> Option<f64> {
+        if a.len() != b.len() || a.is_empty() {
+            return None;
+        }
+
+        match self {
+            SearchMetric::Cosine => {
+                let dot: f64 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+                let norms = a.iter().map(|x| x * x).sum::<f64>().sqrt()
+                    * b.iter().map(|y| y * y).sum::<f64>().sqrt();
+                (norms > 0.0).then(|| 1.0 - dot / norms)
+            }
+            SearchMetric::Euclidean => Some(
+                a.iter()
+                    .zip(b)
+                    .map(|(x, y)| (x - y) * (x - y))
+                    .sum::<f64>()
+                    .sqrt(),
+            ),
+        }
+    }
+}
+
+/// How a vector search result compares to the exact nearest neighbours.
+#[derive(Debug)]
+pub struct RankingComparison {
+    /// How many of the exact neighbours the search returned.
+    pub found: usize,
+    /// Share of the exact neighbours the search returned.
+    pub recall: f64,
+    /// Whether the neighbours the search did return are in the exact order.
+    pub order_matches: bool,
+}
+
+/// The cities of the `k` embeddings recorded in instance.json that are
+/// nearest to `query`, nearest first. Embeddings of cities that have since
+/// been deleted are left out.
+pub fn nearest_cities(query: &[f64], k: usize, metric: SearchMetric) -> Vec<String> {
+    let instance_data = load_instance_data();
+    let created = &instance_data["created_entities"];
+    let cities: HashSet<&str> = created["cities"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|city| city["id"].as_str())
+        .collect();
+
+    let mut ranked: Vec<(f64, &str)> = created["embeddings"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|embedding| {
+            let city_id = embedding["city_id"].as_str()?;
+            let vector: Vec<f64> = serde_json::from_value(embedding["vector"].clone()).ok()?;
+            let distance = metric.distance(query, &vector)?;
+            cities.contains(city_id).then_some((distance, city_id))
+        })
+        .collect();
+    ranked.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    ranked
+        .into_iter()
+        .take(k)
+        .map(|(_, city_id)| city_id.to_string())
+        .collect()
+}
+
+pub fn compare_ranking(expected: &[String], returned: &[String]) -> RankingComparison {
+    let shared_in_returned: Vec<&String> =
+        returned.iter().filter(|id| expected.contains(id)).collect();
+    let shared_in_expected: Vec<&String> =
+        expected.iter().filter(|id| returned.contains(id)).collect();
+
+    let found: HashSet<&String> = shared_in_returned.iter().copied().collect();
+    RankingComparison {
+        found: found.len(),
+        recall: if expected.is_empty() {
+            1.0
+        } else {
+            found.len() as f64 / expected.len() as f64
+        },
+        order_matches: shared_in_returned == shared_in_expected,
+    }
+}
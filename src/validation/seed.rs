@@ -0,0 +1,50 @@
+use std::collections::HashMap;
+
+use crate::validation::{ParsedQueries, ParsedSchema};
+
+/// Prepended to the reference queries deployed for seeding, so they never
+/// clash with the learner's own queries of the same name.
+pub const SEED_QUERY_PREFIX: &str = "helixir_seed_";
+
+/// The embedded reference answers `seed` replays lessons with: the final
+/// schema and the reference queries of each lesson. Not every lesson keeps
+/// the queries of the ones before it, so all of them are merged.
+pub struct SeedReference<'a> {
+    pub schema: &'a str,
+    pub queries: Vec<&'a str>,
+}
+
+impl SeedReference<'_> {
+    /// The reference queries renamed with [`SEED_QUERY_PREFIX`], or `None`
+    /// when the learner's schema differs from the reference one and the
+    /// queries would not compile against it.
+    pub fn queries_for(&self, schema: &ParsedSchema) -> Option<String> {
+        let reference_schema = ParsedSchema::from_string(self.schema).ok()?;
+        if !schema.validate_answer(&reference_schema).is_correct {
+            return None;
+        }
+
+        let mut reference = HashMap::new();
+        for answer in &self.queries {
+            reference.extend(ParsedQueries::from_string(answer).ok()?.queries);
+        }
+        let mut queries: Vec<_> = reference.values().collect();
+        queries.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Some(
+            queries
+                .into_iter()
+                .map(|query| {
+                    format!(
+                        "QUERY {}{} ({}) =>\n    {}",
+                        SEED_QUERY_PREFIX,
+                        query.name,
+                        query.parameters,
+                        query.body.replace('\n', "\n    ")
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n\n"),
+        )
+    }
+}
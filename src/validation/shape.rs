@@ -0,0 +1,247 @@
+use std::collections::HashSet;
+
+use serde_json::Value;
+
+use crate::validation::{ParsedSchema, Property};
+
+const NODE_FIELDS: &[&str] = &["id", "label"];
+const EDGE_FIELDS: &[&str] = &["id", "label", "from_node", "to_node"];
+const VECTOR_FIELDS: &[&str] = &["id", "label", "data", "score"];
+
+/// One field of a returned node, edge or vector that does not fit the schema.
+#[derive(Debug, Clone)]
+pub struct ShapeMismatch {
+    /// e.g. `Country 'uk_country_id'`
+    pub element: String,
+    pub field: String,
+    pub problem: String,
+    /// `id`, `label` and the other fields HelixDB adds itself, as opposed to
+    /// the properties declared in schema.hx.
+    pub system: bool,
+    /// Set for schema properties the element does not have. Queries are free
+    /// to leave a property unset, so these only matter when the lesson reads
+    /// the property.
+    pub unset: bool,
+}
+
+enum ElementKind {
+    Node,
+    Edge,
+    Vector,
+}
+
+impl ParsedSchema {
+    /// Checks every node, edge and vector in a query response against the
+    /// types declared in the schema. Anything with an `id` counts as an
+    /// element; property projections without one are left alone.
+    pub fn check_response_shape(&self, response: &Value) -> Vec<ShapeMismatch> {
+        let mut mismatches = Vec::new();
+        self.check_value(response, &mut mismatches);
+        mismatches
+    }
+
+    fn check_value(&self, value: &Value, mismatches: &mut Vec<ShapeMismatch>) {
+        match value {
+            Value::Array(items) => {
+                for item in items {
+                    self.check_value(item, mismatches);
+                }
+            }
+            Value::Object(fields) if fields.contains_key("id") => {
+                self.check_element(value, mismatches)
+            }
+            Value::Object(fields) => {
+                for field in fields.values() {
+                    self.check_value(field, mismatches);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn check_element(&self, element: &Value, mismatches: &mut Vec<ShapeMismatch>) {
+        let id = element["id"].as_str().unwrap_or("?");
+        let Some((label, kind, properties)) = self.element_type(element) else {
+            mismatches.push(ShapeMismatch {
+                element: format!("'{}'", id),
+                field: "label".to_string(),
+                problem: match &element["label"] {
+                    Value::String(label) => {
+                        format!("is '{}', which is not a type in your schema", label)
+                    }
+                    Value::Null => {
+                        "is missing, and its properties do not single out one type".to_string()
+                    }
+                    other => format!("is {}, expected a type name", describe(other)),
+                },
+                system: true,
+                unset: false,
+            });
+            return;
+        };
+        let name = format!("{} '{}'", label, id);
+
+        let system_fields = match kind {
+            ElementKind::Node => NODE_FIELDS,
+            ElementKind::Edge => EDGE_FIELDS,
+            ElementKind::Vector => VECTOR_FIELDS,
+        };
+        for field in system_fields {
+            let value = &element[*field];
+            let problem = match *field {
+                // similarity is only attached to search results
+                "score" if value.is_null() => None,
+                "score" => (!value.is_number())
+                    .then(|| format!("is {}, expected a number", describe(value))),
+                "data" => (!matches_type(value, "[F64]"))
+                    .then(|| format!("is {}, expected a list of numbers", describe(value))),
+                _ => (!value.is_string())
+                    .then(|| format!("is {}, expected a string", describe(value))),
+            };
+            if let Some(problem) = problem {
+                mismatches.push(ShapeMismatch {
+                    element: name.clone(),
+                    field: field.to_string(),
+                    problem,
+                    system: true,
+                    unset: false,
+                });
+            }
+        }
+
+        let mut properties: Vec<&Property> = properties
+            .iter()
+            // the embedding of a vector is returned as `data`, not by name
+            .filter(|p| !matches!(kind, ElementKind::Vector) || p.prop_type != "[F64]")
+            .collect();
+        properties.sort_by(|a, b| a.name.cmp(&b.name));
+        for property in properties {
+            let value = &element[property.name.as_str()];
+            let problem = if value.is_null() {
+                format!("is missing, expected {}", property.prop_type)
+            } else if !matches_type(value, &property.prop_type) {
+                format!("is {}, expected {}", describe(value), property.prop_type)
+            } else {
+                continue;
+            };
+            mismatches.push(ShapeMismatch {
+                element: name.clone(),
+                field: property.name.clone(),
+                problem,
+                system: false,
+                unset: value.is_null(),
+            });
+        }
+    }
+
+    /// The schema type of `element`, by its label or, when that is missing,
+    /// by the type whose properties cover the returned ones.
+    fn element_type(&self, element: &Value) -> Option<(String, ElementKind, &HashSet<Property>)> {
+        if let Some(label) = element["label"].as_str() {
+            if let Some(properties) = self.nodes.get(label) {
+                return Some((label.to_string(), ElementKind::Node, properties));
+            }
+            if let Some(edge) = self.edges.get(label) {
+                return Some((label.to_string(), ElementKind::Edge, &edge.properties));
+            }
+            return self
+                .vectors
+                .get(label)
+                .map(|properties| (label.to_string(), ElementKind::Vector, properties));
+        }
+
+        let fields: Vec<&String> = element.as_object()?.keys().collect();
+        let covers = |properties: &HashSet<Property>, system: &[&str]| {
+            fields.iter().all(|field| {
+                system.contains(&field.as_str()) || properties.iter().any(|p| &&p.name == field)
+            })
+        };
+        let mut candidates: Vec<(String, ElementKind, &HashSet<Property>)> = Vec::new();
+        if element.get("from_node").is_some() {
+            for (label, edge) in &self.edges {
+                if covers(&edge.properties, EDGE_FIELDS) {
+                    candidates.push((label.clone(), ElementKind::Edge, &edge.properties));
+                }
+            }
+        } else if element.get("data").is_some() {
+            for (label, properties) in &self.vectors {
+                if covers(properties, VECTOR_FIELDS) {
+                    candidates.push((label.clone(), ElementKind::Vector, properties));
+                }
+            }
+        } else {
+            for (label, properties) in &self.nodes {
+                if covers(properties, NODE_FIELDS) {
+                    candidates.push((label.clone(), ElementKind::Node, properties));
+                }
+            }
+        }
+
+        // an ambiguous match would blame the wrong type, so only a unique one counts
+        if candidates.len() != 1 {
+            return None;
+        }
+        let (label, kind, properties) = candidates.pop()?;
+        Some((format!("{} (unlabelled)", label), kind, properties))
+    }
+}
+
+/// Explains the mismatches field by field, schema properties first.
+pub fn describe_shape_mismatches(query_name: &str, mismatches: &[ShapeMismatch]) -> String {
+    let mut lines = vec![format!(
+        "The response of {} does not match your schema:",
+        query_name
+    )];
+    let (system, properties): (Vec<_>, Vec<_>) = mismatches.iter().partition(|m| m.system);
+    for mismatch in properties {
+        lines.push(format!(
+            "  {}: {} {}",
+            mismatch.element, mismatch.field, mismatch.problem
+        ));
+    }
+    if !system.is_empty() {
+        lines.push("  System fields (set by HelixDB, not by your schema):".to_string());
+        for mismatch in system {
+            lines.push(format!(
+                "    {}: {} {}",
+                mismatch.element, mismatch.field, mismatch.problem
+            ));
+        }
+    }
+    lines.join("\n")
+}
+
+fn matches_type(value: &Value, prop_type: &str) -> bool {
+    if let Some(inner) = prop_type
+        .strip_prefix('[')
+        .and_then(|t| t.strip_suffix(']'))
+    {
+        return value
+            .as_array()
+            .is_some_and(|items| items.iter().all(|item| matches_type(item, inner.trim())));
+    }
+    match prop_type {
+        "I8" | "I16" | "I32" | "I64" => value.is_i64(),
+        "U8" | "U16" | "U32" | "U64" | "U128" => value.is_u64(),
+        "F32" | "F64" => value.is_number(),
+        "String" | "ID" | "Date" => value.is_string(),
+        "Boolean" => value.is_boolean(),
+        // types helixir does not know yet are not held against the learner
+        _ => true,
+    }
+}
+
+fn describe(value: &Value) -> String {
+    match value {
+        Value::Null => "missing".to_string(),
+        Value::Bool(b) => format!("a boolean ({})", b),
+        Value::Number(n) if n.is_f64() => format!("a decimal number ({})", n),
+        Value::Number(n) => format!("a whole number ({})", n),
+        Value::String(s) => format!("a string (\"{}\")", s),
+        Value::Array(items) => match items.iter().find(|item| !item.is_array()) {
+            Some(item) => format!("a list containing {}", describe(item)),
+            None => "a list".to_string(),
+        },
+        Value::Object(_) => "an object".to_string(),
+    }
+}
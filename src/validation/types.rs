@@ -1,6 +1,8 @@
 use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
 
 use helix_rs::HelixDB;
+use serde::Deserialize;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Property {
@@ -15,6 +17,52 @@ pub struct EdgeInfo {
     pub properties: HashSet<Property>,
 }
 
+/// Nodes of one type that share a name, e.g. Europe created by several checks.
+#[derive(Debug, Clone)]
+pub struct DuplicateNodes {
+    pub label: String,
+    pub name: String,
+    /// Ids of every copy but the one that stays: the copy helixir recorded,
+    /// or else the first.
+    pub extra: Vec<String>,
+}
+
+/// An extra run of a lesson query, from the `cases` of its test in
+/// query_answers, e.g. a filter value that must match nothing.
+#[derive(Debug, Deserialize)]
+pub struct QueryCase {
+    pub description: String,
+    #[serde(default)]
+    pub input: serde_json::Value,
+    #[serde(default)]
+    pub expect: CaseExpectation,
+}
+
+/// What the items of a case's result must look like. Items are matched by
+/// their `name` property.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CaseExpectation {
+    #[serde(default)]
+    pub empty: bool,
+    pub count: Option<usize>,
+    #[serde(default)]
+    pub contains: Vec<String>,
+    #[serde(default)]
+    pub excludes: Vec<String>,
+    /// The whole result set, in any order. Each item is compared on the
+    /// properties it lists, with numbers compared within tolerance.
+    pub results: Option<Vec<serde_json::Value>>,
+}
+
+/// An entity recorded in instance.json that the instance no longer has.
+#[derive(Debug)]
+pub struct StaleEntity {
+    pub label: String,
+    pub id: String,
+    pub name: Option<String>,
+}
+
 #[derive(Debug)]
 pub struct ParsedSchema {
     pub nodes: HashMap<String, HashSet<Property>>,
@@ -32,6 +80,12 @@ pub struct PropertyErrors {
 #[derive(Debug)]
 pub struct QueryValidator {
     pub(crate) client: HelixDB,
+    /// Prepended to lesson query names, to run the seed queries instead of
+    /// the learner's.
+    pub(crate) query_prefix: &'static str,
+    /// The learner's schema, read once per validator when the first response
+    /// is checked against it. `None` when it could not be parsed.
+    pub(crate) schema: OnceLock<Option<ParsedSchema>>,
 }
 
 pub struct ValidationResult {
@@ -58,7 +112,6 @@ pub struct EdgeErrors {
 
 #[derive(Debug, Clone)]
 pub struct ParsedQuery {
-    #[allow(dead_code)]
     pub name: String,
     pub parameters: String,
     pub body: String,
@@ -73,5 +126,31 @@ pub struct QueryValidationResult {
     pub is_correct: bool,
     pub missing_queries: Vec<String>,
     pub extra_queries: Vec<String>,
-    pub query_errors: HashMap<String, String>,
+    pub query_errors: HashMap<String, QueryMismatch>,
+}
+
+/// Why a query differs from the lesson's, with both sources for a diff.
+pub struct QueryMismatch {
+    pub errors: Vec<String>,
+    pub expected_source: String,
+    pub actual_source: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub file: Option<String>,
+    pub line: Option<usize>,
+    pub message: String,
+    pub suggestion: Option<String>,
+}
+
+pub struct DeployReport {
+    pub success: bool,
+    /// The learner interrupted the deploy with Ctrl-C.
+    pub cancelled: bool,
+    /// Nothing changed since the last successful deploy, so it was not rerun.
+    pub skipped: bool,
+    pub diagnostics: Vec<Diagnostic>,
+    /// Raw stdout/stderr of every helix command that was run.
+    pub log: String,
 }
@@ -1,5 +1,83 @@
-use std::path::Path;
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::project::project;
+use crate::validation::HELPER_FILE;
 
 pub fn check_helix_init() -> bool {
-    Path::new("db/schema.hx").exists() && Path::new("db/queries.hx").exists()
+    !hx_files(&project().queries_dir).is_empty()
+}
+
+/// Every .hx file directly inside `dir`, sorted so merged results are stable.
+/// helixir's own helper file is left out.
+pub fn hx_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "hx"))
+        .filter(|path| path.file_name().is_none_or(|name| name != HELPER_FILE))
+        .collect();
+    files.sort();
+    files
+}
+
+/// Reads every .hx file in `dir`, paired with its path relative to the project root.
+pub fn read_hx_sources(dir: &Path) -> Result<Vec<(String, String)>, String> {
+    let files = hx_files(dir);
+    if files.is_empty() {
+        return Err(format!("No .hx files found in '{}'", display_path(dir)));
+    }
+
+    files
+        .iter()
+        .map(|path| {
+            fs::read_to_string(path)
+                .map(|content| (display_path(path), content))
+                .map_err(|e| format!("Failed to read file '{}': {}", display_path(path), e))
+        })
+        .collect()
+}
+
+/// `path` relative to the project root, for messages and persisted state.
+pub fn display_path(path: &Path) -> String {
+    path.strip_prefix(&project().root)
+        .unwrap_or(path)
+        .display()
+        .to_string()
+}
+
+/// Errors with every definition that appears more than once, given as
+/// `(name, "file:line")` pairs in source order.
+pub fn check_duplicates(definitions: Vec<(String, String)>) -> Result<(), String> {
+    let mut locations: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (name, location) in definitions {
+        locations.entry(name).or_default().push(location);
+    }
+
+    let duplicates: Vec<String> = locations
+        .into_iter()
+        .filter(|(_, sites)| sites.len() > 1)
+        .map(|(name, sites)| format!("{} is defined at {}", name, sites.join(" and ")))
+        .collect();
+
+    if duplicates.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("Duplicate definitions: {}", duplicates.join("; ")))
+    }
+}
+
+/// A hash that is stable across Rust versions and platforms, unlike
+/// `DefaultHasher`, for anything helixir persists or needs to reproduce.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    })
 }